@@ -19,7 +19,7 @@
 //!
 //!Using the `nslogger_client` API:
 //!
-//!```rust
+//!```rust,no_run
 //! use nslogger::{Logger, Domain};
 //! use log::Level;
 //!
@@ -31,50 +31,47 @@
 //! }
 //! ```
 //!
+//!Selecting the destination of the messages:
+//!
+//!```rust,no_run
+//! use nslogger::{BonjourServiceType, ConnectionMode, Logger};
+//!
+//! fn main() -> Result<(), nslogger::Error> {
+//!  let log = Logger::with_options(
+//!      log::LevelFilter::Info,
+//!      ConnectionMode::Bonjour(BonjourServiceType::Default(false)),
+//!      false,
+//!  )?;
+//!  log.set_remote_host("127.0.0.1", 50000, true)?;
+//!  Ok(())
+//! }
+//! ```
+//!
 //!## NOT supported:
 //!
 //!At the moment there are no plans to add support for the following NSLogger features:
 //!
-//! - message blocks
 //! - client disconnects
-use std::{env, path::PathBuf, str::FromStr};
+use std::{backtrace::Backtrace, panic, path::Path, sync::Once, thread, time::Duration};
 
+#[macro_use]
+mod macros;
 mod nslogger;
 
-use nslogger::ConnectionMode;
-pub use nslogger::{Domain, Logger};
-
-/// Parses the environment variables to identify the max logging level, the type of connection to
-/// NSLogger (or the log file path), and whether the logger should wait for each message to be
-/// handled before returning from the log calls.
-fn parse_env() -> (log::LevelFilter, ConnectionMode, bool) {
-    let connection_mode = if let Ok(val) = env::var("NSLOG_FILENAME") {
-        PathBuf::from_str(&val)
-            .map(ConnectionMode::File)
-            .unwrap_or_default()
-    } else {
-        let use_ssl = env::var("NSLOG_USE_SSL").map(|v| v != "0").unwrap_or(true);
-        if let Ok(val) = env::var("NSLOG_HOST") {
-            val.split_once(':')
-                .map(|(host, port)| {
-                    u16::from_str(port)
-                        .map(|p| ConnectionMode::Tcp(host.to_string(), p, use_ssl))
-                        .unwrap_or_default()
-                })
-                .unwrap_or_default() // ConnectionMode::Tcp((), (), ())
-        } else {
-            let service_type = env::var("NSLOG_BONJOUR_SERVICE")
-                .map(|s| nslogger::BonjourServiceType::Custom(s, use_ssl))
-                .unwrap_or(nslogger::BonjourServiceType::Default(use_ssl));
-            ConnectionMode::Bonjour(service_type)
-        }
-    };
-    let flush_messages = env::var("NSLOG_FLUSH")
-        .map(|v| v == "1")
-        .unwrap_or_default();
-    let raw_level = env::var("NSLOG_LEVEL").unwrap_or("WARN".to_string());
-    let level_filter = log::LevelFilter::from_str(&raw_level).unwrap_or(log::LevelFilter::Warn);
-    (level_filter, connection_mode, flush_messages)
+#[cfg(feature = "slog")]
+pub use nslogger::NSLoggerDrain;
+#[cfg(feature = "tracing")]
+pub use nslogger::NSLoggerLayer;
+pub use nslogger::{
+    with_context, with_context_async, BlockGuard, BonjourServiceType, ConnectionMode,
+    ConnectionState, ConnectionStatus, Domain, DomainMapping, Error, KeyValueText, Logger,
+    LoggerBuilder, LoggerConfig, LoggerStats, TargetFilter, WithContext, WorkerState, WorkerStatus,
+};
+
+/// Items used by the exported macros.
+#[doc(hidden)]
+pub mod __private {
+    pub use log::Level;
 }
 
 /// Initializes the global logger with a Logger instance.
@@ -82,133 +79,169 @@ fn parse_env() -> (log::LevelFilter, ConnectionMode, bool) {
 /// This should be called early in the execution of a Rust program, and the
 /// global logger may only be initialized once. Future initialization
 /// attempts will return an error.
-pub fn init() -> Result<(), log::SetLoggerError> {
-    let (filter, connection_mode, flush_messages) = parse_env();
-    let logger = Logger::with_options(filter, connection_mode, flush_messages).unwrap();
-    log::set_boxed_logger(Box::new(logger))?;
-    log::set_max_level(filter);
+///
+/// The logger is configured from the `NSLOG_*` environment variables, e.g. `NSLOG_HOST` or
+/// `NSLOG_LEVEL`, on top of the TOML file named by `NSLOG_CONFIG` (with the `config` feature).
+/// Invalid values are reported as errors.
+///
+/// The queued messages are drained when the process exits normally, see [`shutdown`].
+pub fn init() -> Result<(), Error> {
+    init_with_config(LoggerConfig::load()?)
+}
+
+/// Same as [`init`], but reads the given TOML file instead of the one named by `NSLOG_CONFIG`.
+/// The environment variables still take precedence over the values of the file.
+#[cfg(feature = "config")]
+pub fn init_from_file(path: impl AsRef<Path>) -> Result<(), Error> {
+    init_with_config(LoggerConfig::from_file(path)?.with_env()?)
+}
+
+fn init_with_config(config: LoggerConfig) -> Result<(), Error> {
+    let logger = LoggerBuilder::from_config(config)?.build()?;
+    nslogger::set_global_logger(logger)?;
+    shutdown_at_exit();
     Ok(())
 }
 
-#[cfg(test)]
-mod tests {
-    use std::{fs::File, io::Read, time::Duration};
+/// Returns the global logger installed by [`init`], e.g. to change its filter at runtime with
+/// [`Logger::set_filter`].
+pub fn logger() -> Option<&'static Logger> {
+    nslogger::global_logger()
+}
 
-    use log::Level;
-    use serial_test::serial;
-    use tempfile::NamedTempFile;
+/// Same as [`init`], but also returns a guard that drains the queued messages when dropped, e.g.
+/// at the end of `main`.
+pub fn init_with_guard() -> Result<ShutdownGuard, Error> {
+    init()?;
+    Ok(ShutdownGuard::default())
+}
 
-    use super::*;
-    use crate::nslogger::{
-        BonjourServiceType, ConnectionMode, Domain, LogMessageType, Logger, MessagePartKey,
-        MessagePartType, SEQUENCE_NB_OFFSET,
-    };
+/// Waits for the messages queued by all the loggers to be written, then closes their connections.
+/// Messages logged afterwards are discarded.
+///
+/// Returns `false` if the messages couldn't be written before the timeout expired, e.g. when the
+/// desktop viewer couldn't be reached.
+pub fn shutdown(timeout: Duration) -> bool {
+    nslogger::shutdown(timeout)
+}
 
-    #[test]
-    #[serial]
-    fn parses_default_env() {
-        assert_eq!(
-            (log::LevelFilter::Warn, ConnectionMode::default(), false),
-            parse_env()
-        )
-    }
+/// Drains the queued messages and exits the process with the conventional exit code when
+/// receiving SIGINT or SIGTERM (only Ctrl-C on non-Unix platforms).
+#[cfg(feature = "signals")]
+pub fn install_signal_handlers() -> Result<(), Error> {
+    nslogger::install_signal_handlers()
+}
 
-    #[test]
-    #[serial]
-    fn parses_file_path_from_env() {
-        unsafe {
-            env::set_var("NSLOG_FILENAME", "/tmp/file_output.log");
-        }
-        assert_eq!(
-            (
-                log::LevelFilter::Warn,
-                ConnectionMode::File(PathBuf::from("/tmp/file_output.log")),
-                false
-            ),
-            parse_env()
+/// Installs a panic hook that logs the panic message, location and backtrace as an error, followed
+/// by a mark, and waits for these messages to be written before calling the previous hook.
+///
+/// The messages are sent through the global logger installed by [`init`], if any, or else through a
+/// new logger configured the same way. See [`install_panic_hook_with`] to use a given logger
+/// instead.
+pub fn install_panic_hook() -> Result<(), Error> {
+    let logger = match logger() {
+        Some(logger) => logger.clone(),
+        None => LoggerBuilder::from_config(LoggerConfig::load()?)?.build()?,
+    };
+    install_panic_hook_with(logger);
+    Ok(())
+}
+
+/// Same as [`install_panic_hook`], but sends the messages through the given logger.
+pub fn install_panic_hook_with(logger: Logger) {
+    let previous_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let thread = thread::current();
+        let thread_name = thread.name().unwrap_or("<unnamed>");
+        let payload = info
+            .payload()
+            .downcast_ref::<&str>()
+            .copied()
+            .or_else(|| info.payload().downcast_ref::<String>().map(String::as_str))
+            .unwrap_or("Box<dyn Any>");
+        let location = info
+            .location()
+            .map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column()))
+            .unwrap_or_default();
+        let message = format!(
+            "thread '{thread_name}' panicked at {location}:\n{payload}\n\nstack backtrace:\n{}",
+            Backtrace::force_capture()
         );
-        unsafe {
-            env::remove_var("NSLOG_FILENAME");
-        }
+        logger.log_panic(
+            info.location().map(|l| Path::new(l.file())),
+            info.location().map(|l| l.line()),
+            &message,
+            &format!("panic in thread '{thread_name}'"),
+        );
+        previous_hook(info);
+    }));
+}
+
+/// Calls [`shutdown`] when dropped.
+#[must_use = "the queued messages are drained when the guard is dropped"]
+#[derive(Debug)]
+pub struct ShutdownGuard {
+    timeout: Duration,
+}
+
+impl ShutdownGuard {
+    /// Sets the maximum time spent draining the queued messages.
+    pub fn with_timeout(timeout: Duration) -> Self {
+        Self { timeout }
     }
+}
 
-    #[test]
-    #[serial]
-    fn parses_log_level_from_env() {
-        unsafe {
-            env::set_var("NSLOG_LEVEL", "INFO");
-        }
-        assert_eq!(
-            (log::LevelFilter::Info, ConnectionMode::default(), false),
-            parse_env()
-        );
-        unsafe {
-            env::remove_var("NSLOG_LEVEL");
-        }
+impl Default for ShutdownGuard {
+    fn default() -> Self {
+        Self::with_timeout(nslogger::DEFAULT_SHUTDOWN_TIMEOUT)
     }
+}
 
-    #[test]
-    #[serial]
-    fn disables_bonjour_ssl_from_env() {
-        unsafe {
-            env::set_var("NSLOG_USE_SSL", "0");
-        }
-        assert_eq!(
-            (
-                log::LevelFilter::Warn,
-                ConnectionMode::Bonjour(BonjourServiceType::Default(false)),
-                false
-            ),
-            parse_env()
-        );
-        unsafe {
-            env::remove_var("NSLOG_USE_SSL");
-        }
+impl Drop for ShutdownGuard {
+    fn drop(&mut self) {
+        shutdown(self.timeout);
     }
+}
 
-    #[test]
-    #[serial]
-    fn sets_remote_host_from_env() {
-        unsafe {
-            env::set_var("NSLOG_HOST", "127.0.0.1:50000");
-        }
-        assert_eq!(
-            (
-                log::LevelFilter::Warn,
-                ConnectionMode::Tcp("127.0.0.1".to_string(), 50000, true),
-                false
-            ),
-            parse_env()
-        );
-        unsafe {
-            env::remove_var("NSLOG_HOST");
-        }
+/// Registers [`shutdown`] to be called when the process exits normally, i.e. when returning from
+/// `main` or calling [`std::process::exit`].
+fn shutdown_at_exit() {
+    static REGISTER: Once = Once::new();
+
+    unsafe extern "C" {
+        fn atexit(callback: extern "C" fn()) -> std::ffi::c_int;
     }
 
-    #[test]
-    #[serial]
-    fn sets_message_flushing_from_env() {
-        unsafe {
-            env::set_var("NSLOG_FLUSH", "1");
-        }
-        assert_eq!(
-            (log::LevelFilter::Warn, ConnectionMode::default(), true),
-            parse_env()
-        );
-        unsafe {
-            env::remove_var("NSLOG_FLUSH");
-        }
+    extern "C" fn drain_queue() {
+        shutdown(nslogger::DEFAULT_SHUTDOWN_TIMEOUT);
     }
 
+    REGISTER.call_once(|| unsafe {
+        atexit(drain_queue);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, fs::File, io::Read, str::FromStr, time::Duration};
+
+    use log::Level;
+    use serial_test::serial;
+    use tempfile::NamedTempFile;
+
+    use super::*;
+    use crate::nslogger::{LogMessageType, MessagePartKey, MessagePartType, SEQUENCE_NB_OFFSET};
+
     #[test]
     #[serial]
     fn logs_to_file() {
         let tempfile = NamedTempFile::new().expect("temp file");
         let file_path = tempfile.into_temp_path();
-        let mut log = Logger::new().expect("logger instance");
-        log.set_message_flushing(true);
-        log.set_log_file_path(file_path.to_str().unwrap())
-            .expect("setting file path"); // File extension is constrained!!
+        let log = LoggerBuilder::new()
+            .log_file_path(file_path.to_path_buf())
+            .flush_messages(true)
+            .build()
+            .expect("logger instance");
         let first_msg = "message logged to file";
         log.logm(Some(Domain::App), Level::Warn, first_msg);
         log.logm(
@@ -286,6 +319,368 @@ mod tests {
         assert_eq!(last_msg_idx + last_msg_size + 4, buf.len());
     }
 
+    #[test]
+    #[serial]
+    fn logs_function_names() {
+        let file_path = NamedTempFile::new().expect("temp file").into_temp_path();
+        let log = LoggerBuilder::new()
+            .filter(log::LevelFilter::Info)
+            .log_file_path(file_path.to_path_buf())
+            .build()
+            .expect("logger instance");
+        log::Log::log(
+            &log,
+            &log::Record::builder()
+                .args(format_args!("record message"))
+                .level(Level::Info)
+                .target("my_app::db")
+                .module_path(Some("my_app::db::pool"))
+                .build(),
+        );
+        let closure = || logl!(log, None, Level::Warn, "macro message {}", 1);
+        closure();
+        assert!(log.flush(Duration::from_secs(5)));
+
+        let mut buf = Vec::new();
+        File::open(&file_path)
+            .expect("file should exist")
+            .read_to_end(&mut buf)
+            .expect("file read");
+        let contents = String::from_utf8_lossy(&buf);
+        assert!(contents.contains("my_app::db::pool"));
+        assert!(contents.contains("nslogger::tests::logs_function_names"));
+        assert!(!contents.contains("{{closure}}"));
+        assert!(contents.contains("src/lib.rs"));
+        assert!(contents.ends_with("macro message 1"));
+    }
+
+    #[test]
+    #[serial]
+    fn logs_key_values() {
+        let file_path = NamedTempFile::new().expect("temp file").into_temp_path();
+        let log = LoggerBuilder::new()
+            .filter(log::LevelFilter::Info)
+            .log_file_path(file_path.to_path_buf())
+            .key_value_text(KeyValueText::Logfmt)
+            .build()
+            .expect("logger instance");
+        let pairs: [(&str, log::kv::Value); 2] = [("user_id", 42.into()), ("name", "jane".into())];
+        log::Log::log(
+            &log,
+            &log::Record::builder()
+                .args(format_args!("user logged in"))
+                .level(Level::Info)
+                .key_values(&pairs)
+                .build(),
+        );
+        assert!(log.flush(Duration::from_secs(5)));
+
+        let mut buf = Vec::new();
+        File::open(&file_path)
+            .expect("file should exist")
+            .read_to_end(&mut buf)
+            .expect("file read");
+        let mut user_id_part = vec![MessagePartKey::KeyValue as u8];
+        user_id_part.push(MessagePartType::Int64 as u8);
+        user_id_part.extend_from_slice(&42u64.to_be_bytes());
+        assert!(buf
+            .windows(user_id_part.len())
+            .any(|part| part == user_id_part));
+        let contents = String::from_utf8_lossy(&buf);
+        assert!(contents.contains("user logged in user_id=42 name=jane"));
+        assert!(contents.ends_with("jane"));
+    }
+
+    #[test]
+    #[serial]
+    fn logs_with_macros() {
+        struct Counted<'a>(&'a Cell<u32>);
+
+        impl std::fmt::Display for Counted<'_> {
+            fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                self.0.set(self.0.get() + 1);
+                f.write_str("counted")
+            }
+        }
+
+        let file_path = NamedTempFile::new().expect("temp file").into_temp_path();
+        let log = LoggerBuilder::new()
+            .filter(log::LevelFilter::Info)
+            .log_file_path(file_path.to_path_buf())
+            .build()
+            .expect("logger instance");
+        let formatted = Cell::new(0);
+        {
+            let _block = nslog_block!(log, domain: Domain::App, "block {}", 1);
+            nslog!(log, "info {}", Counted(&formatted));
+            nslog!(log, level: Level::Debug, "debug {}", Counted(&formatted));
+            nslog_data!(log, domain: Domain::Network, level: Level::Warn, &[0xde, 0xad]);
+            nslog_image!(log, level: Level::Trace, &[0xbe, 0xef]);
+        }
+        let _skipped = nslog_block!(log, level: Level::Debug, "skipped block");
+        nslog_mark!(log, "mark {}", 2);
+        assert!(log.flush(Duration::from_secs(5)));
+        assert_eq!(1, formatted.get());
+
+        let mut buf = Vec::new();
+        File::open(&file_path)
+            .expect("file should exist")
+            .read_to_end(&mut buf)
+            .expect("file read");
+        let block_start = [MessagePartKey::MessageType as u8, 3, 0, 0, 0, 1];
+        let block_end = [MessagePartKey::MessageType as u8, 3, 0, 0, 0, 2];
+        let count = |part: &[u8]| buf.windows(part.len()).filter(|w| *w == part).count();
+        assert_eq!(1, count(&block_start));
+        assert_eq!(1, count(&block_end));
+        assert_eq!(1, count(&[0xde, 0xad]));
+        assert_eq!(0, count(&[0xbe, 0xef]));
+        let contents = String::from_utf8_lossy(&buf);
+        assert!(contents.contains("block 1"));
+        assert!(contents.contains("info counted"));
+        assert!(contents.contains("nslogger::tests::logs_with_macros"));
+        assert!(!contents.contains("skipped block"));
+        assert!(contents.ends_with("mark 2"));
+    }
+
+    #[test]
+    #[serial]
+    fn logs_context() {
+        let file_path = NamedTempFile::new().expect("temp file").into_temp_path();
+        let log = LoggerBuilder::new()
+            .log_file_path(file_path.to_path_buf())
+            .key_value_text(KeyValueText::Logfmt)
+            .build()
+            .expect("logger instance");
+        with_context(&[("request_id", 42)], || {
+            with_context(&[("tenant", "acme")], || {
+                log.logm(None, Level::Warn, "nested message");
+            });
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .build()
+                .expect("runtime");
+            runtime.block_on(with_context_async(&[("request_id", 43)], async {
+                tokio::task::yield_now().await;
+                log.logm(None, Level::Warn, "async message");
+            }));
+        });
+        log.logm(None, Level::Warn, "message without context");
+        assert!(log.flush(Duration::from_secs(5)));
+
+        let mut buf = Vec::new();
+        File::open(&file_path)
+            .expect("file should exist")
+            .read_to_end(&mut buf)
+            .expect("file read");
+        let mut request_id_part = vec![MessagePartKey::KeyValue as u8];
+        request_id_part.push(MessagePartType::Int64 as u8);
+        request_id_part.extend_from_slice(&42u64.to_be_bytes());
+        assert!(buf
+            .windows(request_id_part.len())
+            .any(|part| part == request_id_part));
+        let contents = String::from_utf8_lossy(&buf);
+        assert!(contents.contains("[request_id=42 tenant=acme] nested message"));
+        assert!(contents.contains("[request_id=43] async message"));
+        assert!(contents.ends_with("message without context"));
+        assert!(!contents.ends_with("] message without context"));
+    }
+
+    #[test]
+    fn leaves_context_out_of_marks() {
+        let file_path = NamedTempFile::new().expect("temp file").into_temp_path();
+        let log = LoggerBuilder::new()
+            .log_file_path(file_path.to_path_buf())
+            .build()
+            .expect("logger instance");
+        with_context(&[("request_id", 42)], || log.log_mark(Some("checkpoint")));
+        assert!(log.flush(Duration::from_secs(5)));
+
+        let mut buf = Vec::new();
+        File::open(&file_path)
+            .expect("file should exist")
+            .read_to_end(&mut buf)
+            .expect("file read");
+        let contents = String::from_utf8_lossy(&buf);
+        assert!(contents.contains("checkpoint"));
+        assert!(!contents.contains("request_id"));
+    }
+
+    #[test]
+    fn shares_logger_handles() {
+        fn assert_handle<T: Clone + Send + Sync + 'static>() {}
+        assert_handle::<Logger>();
+        let domains: std::collections::BTreeSet<_> = ["DB", "App", "my_crate", "DB"]
+            .iter()
+            .map(|name| Domain::from_str(name).unwrap())
+            .collect();
+        assert_eq!(
+            vec![
+                Domain::App,
+                Domain::DB,
+                Domain::Custom("my_crate".to_string())
+            ],
+            domains.into_iter().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn logs_through_child_handles() {
+        let file_path = NamedTempFile::new().expect("temp file").into_temp_path();
+        let log = LoggerBuilder::new()
+            .log_file_path(file_path.to_path_buf())
+            .key_value_text(KeyValueText::Logfmt)
+            .build()
+            .expect("logger instance");
+        let db_log = log
+            .with_domain(Domain::DB)
+            .with_level(Level::Warn)
+            .with_fields(&[("pool", "main")]);
+        db_log.log("child message");
+        nslog!(db_log, "macro message {}", 1);
+        db_log.set_filter(log::LevelFilter::Error);
+        nslog!(db_log, "filtered message");
+        log.logm(None, Level::Error, "parent message");
+        assert!(log.flush(Duration::from_secs(5)));
+        assert_eq!(3, db_log.stats().written);
+        assert_eq!(log.stats().logger_id, db_log.stats().logger_id);
+        let other_path = NamedTempFile::new().expect("temp file").into_temp_path();
+        let other_log = LoggerBuilder::new()
+            .log_file_path(other_path.to_path_buf())
+            .build()
+            .expect("logger instance");
+        assert_ne!(log.stats().logger_id, other_log.stats().logger_id);
+        drop(other_log);
+
+        let mut buf = Vec::new();
+        File::open(&file_path)
+            .expect("file should exist")
+            .read_to_end(&mut buf)
+            .expect("file read");
+        let db_tag = [&[MessagePartKey::Tag as u8, 0, 0, 0, 0, 2][..], b"DB"].concat();
+        assert_eq!(
+            2,
+            buf.windows(db_tag.len()).filter(|w| *w == db_tag).count()
+        );
+        let contents = String::from_utf8_lossy(&buf);
+        assert!(contents.contains("child message pool=main"));
+        assert!(contents.contains("macro message 1 pool=main"));
+        assert!(!contents.contains("filtered message"));
+        assert!(contents.ends_with("parent message"));
+    }
+
+    #[test]
+    #[serial]
+    fn flushes_queued_messages_to_file() {
+        let tempfile = NamedTempFile::new().expect("temp file");
+        let file_path = tempfile.into_temp_path();
+        let log = LoggerBuilder::new()
+            .log_file_path(file_path.to_path_buf())
+            .build()
+            .expect("logger instance");
+        let message = "message flushed to file";
+        log.logm(Some(Domain::App), Level::Warn, message);
+        assert!(log.flush(Duration::from_secs(5)));
+
+        let mut buf = Vec::new();
+        File::open(file_path)
+            .expect("file should exist")
+            .read_to_end(&mut buf)
+            .expect("file read");
+        assert!(buf.ends_with(message.as_bytes()));
+    }
+
+    #[test]
+    #[serial]
+    fn logs_to_separate_files() {
+        let first_path = NamedTempFile::new().expect("temp file").into_temp_path();
+        let second_path = NamedTempFile::new().expect("temp file").into_temp_path();
+        let first_log = LoggerBuilder::new()
+            .log_file_path(first_path.to_path_buf())
+            .client_name("first_client")
+            .build()
+            .expect("first logger");
+        let second_log = LoggerBuilder::new()
+            .log_file_path(second_path.to_path_buf())
+            .client_name("second_client")
+            .build()
+            .expect("second logger");
+        first_log.logm(Some(Domain::App), Level::Warn, "first message");
+        second_log.logm(Some(Domain::App), Level::Warn, "second message");
+        assert!(first_log.flush(Duration::from_secs(5)));
+        assert!(second_log.flush(Duration::from_secs(5)));
+
+        for (file_path, client_name, message, other_message) in [
+            (
+                &first_path,
+                "first_client",
+                "first message",
+                "second message",
+            ),
+            (
+                &second_path,
+                "second_client",
+                "second message",
+                "first message",
+            ),
+        ] {
+            let mut buf = Vec::new();
+            File::open(file_path)
+                .expect("file should exist")
+                .read_to_end(&mut buf)
+                .expect("file read");
+            let contains = |needle: &str| buf.windows(needle.len()).any(|w| w == needle.as_bytes());
+            assert!(contains(client_name));
+            assert!(buf.ends_with(message.as_bytes()));
+            assert!(!contains(other_message));
+            /*
+             * Each logger numbers its messages on its own.
+             */
+            let msg_idx = u32::from_be_bytes(buf[0..4].try_into().unwrap()) as usize + 4;
+            assert_eq!(
+                1,
+                u32::from_be_bytes(
+                    buf[(msg_idx + SEQUENCE_NB_OFFSET)..(msg_idx + SEQUENCE_NB_OFFSET + 4)]
+                        .try_into()
+                        .unwrap()
+                )
+            );
+        }
+    }
+
+    #[test]
+    #[serial]
+    #[cfg(unix)]
+    fn logs_non_utf8_file_names() {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+        let tempfile = NamedTempFile::new().expect("temp file");
+        let file_path = tempfile.into_temp_path();
+        let log = LoggerBuilder::new()
+            .log_file_path(file_path.to_path_buf())
+            .build()
+            .expect("logger instance");
+        log.logl(
+            Some(Path::new(OsStr::from_bytes(b"invalid_\xff.rs"))),
+            Some(1),
+            None,
+            None,
+            Level::Warn,
+            "message with an invalid file name",
+        );
+        assert!(log.flush(Duration::from_secs(5)));
+        assert_eq!(0, log.error_count());
+
+        let mut buf = Vec::new();
+        File::open(file_path)
+            .expect("file should exist")
+            .read_to_end(&mut buf)
+            .expect("file read");
+        let lossy_name = "invalid_\u{FFFD}.rs".as_bytes();
+        assert!(buf.windows(lossy_name.len()).any(|w| w == lossy_name));
+        let raw_name = b"invalid_\xff.rs";
+        assert!(buf.windows(raw_name.len()).any(|w| w == raw_name));
+    }
+
     /*
      * NOTE The following tests all rely on NSLogger to be running. As such, they ignored to
      * avoid issues in CI.
@@ -0,0 +1,315 @@
+/// Expands to the path of the enclosing function, e.g. `my_crate::db::connect`.
+///
+/// Closures are reported as their enclosing function.
+///
+/// ```rust
+/// fn connect() -> &'static str {
+///     nslogger::function_name!()
+/// }
+///
+/// assert!(connect().ends_with("::connect"));
+/// ```
+#[macro_export]
+macro_rules! function_name {
+    () => {{
+        fn f() {}
+        fn type_name_of<T>(_: T) -> &'static str {
+            ::std::any::type_name::<T>()
+        }
+        let name = type_name_of(f);
+        let name = name.strip_suffix("::f").unwrap_or(name);
+        name.trim_end_matches("::{{closure}}")
+    }};
+}
+
+/// Formats and logs a message through [`Logger::logl`](crate::Logger::logl), along with the
+/// file name, line number and function name of the call site.
+///
+/// ```rust,no_run
+/// use log::Level;
+/// use nslogger::{logl, Domain, Logger};
+///
+/// let log = Logger::new().expect("a logger instance");
+/// logl!(log, Some(Domain::DB), Level::Info, "connected to {}", "localhost");
+/// ```
+#[macro_export]
+macro_rules! logl {
+    ($logger:expr, $domain:expr, $level:expr, $($arg:tt)+) => {
+        $logger.logl(
+            ::std::option::Option::Some(::std::path::Path::new(::std::file!())),
+            ::std::option::Option::Some(::std::line!()),
+            ::std::option::Option::Some($crate::function_name!()),
+            $domain,
+            $level,
+            &::std::format!($($arg)+),
+        )
+    };
+}
+
+/// Logs a message along with the file name, line number and function name of the call site. The
+/// domain and level default to those of the logger handle (see [`Logger::with_domain`] and
+/// [`Logger::with_level`]), or to no domain and the `Info` level. The message is formatted only
+/// when the level passes the filter of the logger for the module path of the call site.
+///
+/// [`Logger::with_domain`]: crate::Logger::with_domain
+/// [`Logger::with_level`]: crate::Logger::with_level
+///
+/// ```rust,no_run
+/// use log::Level;
+/// use nslogger::{nslog, Domain, Logger};
+///
+/// let log = Logger::new().expect("a logger instance");
+/// nslog!(log, "{} users", 3);
+/// nslog!(log, level: Level::Warn, "disk almost full");
+/// nslog!(log, domain: Domain::DB, level: Level::Debug, "query took {}ms", 12);
+/// ```
+#[macro_export]
+macro_rules! nslog {
+    (@log $logger:expr, $domain:expr, $level:expr, $($arg:tt)+) => {{
+        let logger = &$logger;
+        let level: ::std::option::Option<$crate::__private::Level> = $level;
+        let level = level
+            .or(logger.default_level())
+            .unwrap_or($crate::__private::Level::Info);
+        if logger.is_enabled(::std::module_path!(), level) {
+            $crate::logl!(logger, $domain, level, $($arg)+);
+        }
+    }};
+    ($logger:expr, domain: $domain:expr, level: $level:expr, $($arg:tt)+) => {
+        $crate::nslog!(
+            @log $logger,
+            ::std::option::Option::Some($domain),
+            ::std::option::Option::Some($level),
+            $($arg)+
+        )
+    };
+    ($logger:expr, domain: $domain:expr, $($arg:tt)+) => {
+        $crate::nslog!(
+            @log $logger,
+            ::std::option::Option::Some($domain),
+            ::std::option::Option::None,
+            $($arg)+
+        )
+    };
+    ($logger:expr, level: $level:expr, $($arg:tt)+) => {
+        $crate::nslog!(
+            @log $logger,
+            ::std::option::Option::None,
+            ::std::option::Option::Some($level),
+            $($arg)+
+        )
+    };
+    ($logger:expr, $($arg:tt)+) => {
+        $crate::nslog!(
+            @log $logger,
+            ::std::option::Option::None,
+            ::std::option::Option::None,
+            $($arg)+
+        )
+    };
+}
+
+/// Logs binary data through [`Logger::log_data`](crate::Logger::log_data), with the same call-site
+/// metadata and options as [`nslog!`]. The data expression is evaluated only when the level passes
+/// the filter.
+///
+/// ```rust,no_run
+/// use nslogger::{nslog_data, Domain, Logger};
+///
+/// let log = Logger::new().expect("a logger instance");
+/// nslog_data!(log, domain: Domain::Network, &[0x01, 0x02]);
+/// ```
+#[macro_export]
+macro_rules! nslog_data {
+    (@log $method:ident, $logger:expr, $domain:expr, $level:expr, $data:expr) => {{
+        let logger = &$logger;
+        let level: ::std::option::Option<$crate::__private::Level> = $level;
+        let level = level
+            .or(logger.default_level())
+            .unwrap_or($crate::__private::Level::Info);
+        if logger.is_enabled(::std::module_path!(), level) {
+            logger.$method(
+                ::std::option::Option::Some(::std::path::Path::new(::std::file!())),
+                ::std::option::Option::Some(::std::line!()),
+                ::std::option::Option::Some($crate::function_name!()),
+                $domain,
+                level,
+                $data,
+            );
+        }
+    }};
+    ($logger:expr, domain: $domain:expr, level: $level:expr, $data:expr) => {
+        $crate::nslog_data!(
+            @log log_data,
+            $logger,
+            ::std::option::Option::Some($domain),
+            ::std::option::Option::Some($level),
+            $data
+        )
+    };
+    ($logger:expr, domain: $domain:expr, $data:expr) => {
+        $crate::nslog_data!(
+            @log log_data,
+            $logger,
+            ::std::option::Option::Some($domain),
+            ::std::option::Option::None,
+            $data
+        )
+    };
+    ($logger:expr, level: $level:expr, $data:expr) => {
+        $crate::nslog_data!(
+            @log log_data,
+            $logger,
+            ::std::option::Option::None,
+            ::std::option::Option::Some($level),
+            $data
+        )
+    };
+    ($logger:expr, $data:expr) => {
+        $crate::nslog_data!(
+            @log log_data,
+            $logger,
+            ::std::option::Option::None,
+            ::std::option::Option::None,
+            $data
+        )
+    };
+}
+
+/// Logs a PNG image through [`Logger::log_image`](crate::Logger::log_image), with the same options
+/// as [`nslog_data!`].
+///
+/// ```rust,no_run
+/// use nslogger::{nslog_image, Logger};
+///
+/// let log = Logger::new().expect("a logger instance");
+/// let png = std::fs::read("screenshot.png").expect("an image");
+/// nslog_image!(log, &png);
+/// ```
+#[macro_export]
+macro_rules! nslog_image {
+    ($logger:expr, domain: $domain:expr, level: $level:expr, $data:expr) => {
+        $crate::nslog_data!(
+            @log log_image,
+            $logger,
+            ::std::option::Option::Some($domain),
+            ::std::option::Option::Some($level),
+            $data
+        )
+    };
+    ($logger:expr, domain: $domain:expr, $data:expr) => {
+        $crate::nslog_data!(
+            @log log_image,
+            $logger,
+            ::std::option::Option::Some($domain),
+            ::std::option::Option::None,
+            $data
+        )
+    };
+    ($logger:expr, level: $level:expr, $data:expr) => {
+        $crate::nslog_data!(
+            @log log_image,
+            $logger,
+            ::std::option::Option::None,
+            ::std::option::Option::Some($level),
+            $data
+        )
+    };
+    ($logger:expr, $data:expr) => {
+        $crate::nslog_data!(
+            @log log_image,
+            $logger,
+            ::std::option::Option::None,
+            ::std::option::Option::None,
+            $data
+        )
+    };
+}
+
+/// Logs a mark through [`Logger::log_mark`](crate::Logger::log_mark), labelled with the formatted
+/// message or with the current date and time. Marks aren't filtered.
+///
+/// ```rust,no_run
+/// use nslogger::{nslog_mark, Logger};
+///
+/// let log = Logger::new().expect("a logger instance");
+/// nslog_mark!(log);
+/// nslog_mark!(log, "request {}", 42);
+/// ```
+#[macro_export]
+macro_rules! nslog_mark {
+    ($logger:expr) => {
+        $logger.log_mark(::std::option::Option::None)
+    };
+    ($logger:expr, $($arg:tt)+) => {
+        $logger.log_mark(::std::option::Option::Some(&::std::format!($($arg)+)))
+    };
+}
+
+/// Starts a block through [`Logger::start_block`](crate::Logger::start_block), with the same
+/// call-site metadata and options as [`nslog!`]. Evaluates to a [`BlockGuard`](crate::BlockGuard)
+/// ending the block when dropped, which doesn't end anything when the level is filtered out.
+///
+/// ```rust,no_run
+/// use nslogger::{nslog, nslog_block, Logger};
+///
+/// let log = Logger::new().expect("a logger instance");
+/// {
+///     let _block = nslog_block!(log, "request {}", 42);
+///     nslog!(log, "inside the block");
+/// }
+/// ```
+#[macro_export]
+macro_rules! nslog_block {
+    (@log $logger:expr, $domain:expr, $level:expr, $($arg:tt)+) => {{
+        let logger = &$logger;
+        let level: ::std::option::Option<$crate::__private::Level> = $level;
+        let level = level
+            .or(logger.default_level())
+            .unwrap_or($crate::__private::Level::Info);
+        if logger.is_enabled(::std::module_path!(), level) {
+            logger.start_block(
+                ::std::option::Option::Some(::std::path::Path::new(::std::file!())),
+                ::std::option::Option::Some(::std::line!()),
+                ::std::option::Option::Some($crate::function_name!()),
+                $domain,
+                level,
+                &::std::format!($($arg)+),
+            )
+        } else {
+            $crate::BlockGuard::default()
+        }
+    }};
+    ($logger:expr, domain: $domain:expr, level: $level:expr, $($arg:tt)+) => {
+        $crate::nslog_block!(
+            @log $logger,
+            ::std::option::Option::Some($domain),
+            ::std::option::Option::Some($level),
+            $($arg)+
+        )
+    };
+    ($logger:expr, domain: $domain:expr, $($arg:tt)+) => {
+        $crate::nslog_block!(
+            @log $logger,
+            ::std::option::Option::Some($domain),
+            ::std::option::Option::None,
+            $($arg)+
+        )
+    };
+    ($logger:expr, level: $level:expr, $($arg:tt)+) => {
+        $crate::nslog_block!(
+            @log $logger,
+            ::std::option::Option::None,
+            ::std::option::Option::Some($level),
+            $($arg)+
+        )
+    };
+    ($logger:expr, $($arg:tt)+) => {
+        $crate::nslog_block!(
+            @log $logger,
+            ::std::option::Option::None,
+            ::std::option::Option::None,
+            $($arg)+
+        )
+    };
+}
@@ -0,0 +1,68 @@
+use cfg_if::cfg_if;
+
+/*
+ * NOTE the channels carrying the messages to the workers have the same API in both modes, except
+ * for receiving, which blocks the worker thread in the `std-thread` mode.
+ */
+cfg_if! {
+    if #[cfg(feature = "std-thread")] {
+        use std::sync::{mpsc, Arc, Weak};
+
+        pub use std::sync::mpsc::SendError;
+
+        /// Sender of a std channel, which is closed once all the senders are dropped, regardless
+        /// of the weak senders.
+        #[derive(Debug)]
+        pub struct UnboundedSender<T>(Arc<mpsc::Sender<T>>);
+
+        impl<T> Clone for UnboundedSender<T> {
+            fn clone(&self) -> Self {
+                Self(self.0.clone())
+            }
+        }
+
+        impl<T> UnboundedSender<T> {
+            pub fn send(&self, message: T) -> Result<(), SendError<T>> {
+                self.0.send(message)
+            }
+
+            pub fn downgrade(&self) -> WeakUnboundedSender<T> {
+                WeakUnboundedSender(Arc::downgrade(&self.0))
+            }
+        }
+
+        #[derive(Debug)]
+        pub struct WeakUnboundedSender<T>(Weak<mpsc::Sender<T>>);
+
+        impl<T> WeakUnboundedSender<T> {
+            pub fn upgrade(&self) -> Option<UnboundedSender<T>> {
+                self.0.upgrade().map(UnboundedSender)
+            }
+
+            pub fn strong_count(&self) -> usize {
+                self.0.strong_count()
+            }
+        }
+
+        pub type UnboundedReceiver<T> = mpsc::Receiver<T>;
+
+        pub fn unbounded_channel<T>() -> (UnboundedSender<T>, UnboundedReceiver<T>) {
+            let (sender, receiver) = mpsc::channel();
+            (UnboundedSender(Arc::new(sender)), receiver)
+        }
+
+        /// Makes the subsequent sends fail.
+        pub fn close<T>(receiver: &mut UnboundedReceiver<T>) {
+            *receiver = mpsc::channel().1;
+        }
+    } else {
+        pub use tokio::sync::mpsc::{
+            unbounded_channel, UnboundedReceiver, UnboundedSender, WeakUnboundedSender,
+        };
+
+        /// Makes the subsequent sends fail.
+        pub fn close<T>(receiver: &mut UnboundedReceiver<T>) {
+            receiver.close();
+        }
+    }
+}
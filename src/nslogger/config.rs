@@ -0,0 +1,563 @@
+use std::{env, path::PathBuf, str::FromStr};
+#[cfg(feature = "config")]
+use std::{fs, path::Path};
+
+use crate::nslogger::{
+    BonjourServiceType, ConnectionMode, Domain, DomainMapping, Error, KeyValueText, LoggerBuilder,
+    TargetFilter,
+};
+
+/// Options of a logger, which can be part of the settings of an application with the `serde`
+/// feature, and are turned into a [`LoggerBuilder`] with [`LoggerBuilder::from_config`].
+///
+/// `nslogger::init` reads them from a TOML file (with the `config` feature) and from the
+/// `NSLOG_*` environment variables, which take precedence. The keys of the file match the
+/// environment variables, e.g.:
+///
+/// ```toml
+/// host = "192.168.1.10:50000"  # or `filename`, or `bonjour_service`
+/// use_ssl = true
+/// tls_ca_file = "/etc/nslogger/viewer.pem"
+/// # max_file_size = 1048576    # rotates the `filename` file
+/// # max_files = 3
+/// level = "warn,my_crate::db=trace"
+/// max_queued_messages = 10000
+///
+/// [domains]
+/// rules = { "my_crate::db" = "DB" }
+/// depth = 2
+/// ```
+///
+/// The options left unset keep the defaults of [`LoggerBuilder`].
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default, deny_unknown_fields))]
+#[non_exhaustive]
+pub struct LoggerConfig {
+    /// File the messages are written to, instead of the desktop viewer.
+    pub filename: Option<PathBuf>,
+    /// Address of the desktop viewer, as `host:port`, instead of looking it up through Bonjour.
+    pub host: Option<String>,
+    /// Bonjour service type of the desktop viewer, instead of the default ones.
+    pub bonjour_service: Option<String>,
+    /// Whether the connection to the desktop viewer uses SSL. Enabled by default.
+    pub use_ssl: Option<bool>,
+    /// See [`LoggerBuilder::tls_verify`].
+    pub tls_verify: Option<bool>,
+    /// See [`LoggerBuilder::tls_ca_file`].
+    pub tls_ca_file: Option<PathBuf>,
+    /// See [`LoggerBuilder::flush_messages`].
+    pub flush: Option<bool>,
+    /// Size past which the log file is rotated, see [`LoggerBuilder::rotate_log_file`].
+    pub max_file_size: Option<u64>,
+    /// Number of rotated log files kept, 1 by default. Requires `max_file_size`.
+    pub max_files: Option<usize>,
+    /// See [`LoggerBuilder::filter`].
+    pub level: Option<TargetFilter>,
+    /// See [`LoggerBuilder::max_queued_messages`].
+    pub max_queued_messages: Option<usize>,
+    /// See [`LoggerBuilder::client_name`].
+    pub client_name: Option<String>,
+    /// See [`LoggerBuilder::client_version`].
+    pub client_version: Option<String>,
+    /// See [`LoggerBuilder::key_value_parts`].
+    pub key_value_parts: Option<bool>,
+    /// See [`LoggerBuilder::key_value_text`].
+    pub key_value_text: Option<KeyValueText>,
+    /// See [`LoggerBuilder::domain_mapping`].
+    pub domains: DomainMapping,
+}
+
+impl LoggerConfig {
+    /// Reads the file named by `NSLOG_CONFIG`, if any, then the environment variables.
+    pub(crate) fn load() -> Result<Self, Error> {
+        let config = match env::var_os("NSLOG_CONFIG") {
+            #[cfg(feature = "config")]
+            Some(path) => Self::from_file(Path::new(&path))?,
+            #[cfg(not(feature = "config"))]
+            Some(_) => {
+                return Err(Error::InvalidConfiguration(
+                    "NSLOG_CONFIG requires the `config` feature".to_string(),
+                ));
+            }
+            None => Self::default(),
+        };
+        config.with_env()
+    }
+
+    /// Reads the options from the given TOML file. Unknown keys and invalid values are reported
+    /// as errors.
+    #[cfg(feature = "config")]
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let invalid =
+            |reason: String| Error::InvalidConfiguration(format!("{}: {reason}", path.display()));
+        let text = fs::read_to_string(path).map_err(|e| invalid(e.to_string()))?;
+        toml::from_str(&text).map_err(|e| invalid(e.to_string()))
+    }
+
+    /// Overrides the options with the environment variables:
+    /// - `NSLOG_FILENAME`, `NSLOG_HOST` (`host:port`) or `NSLOG_BONJOUR_SERVICE` replace the
+    ///   destination of the messages;
+    /// - `NSLOG_USE_SSL=0` disables SSL, and `NSLOG_FLUSH=1` waits for each message to be written
+    ///   (both accept `0`, `1`, `false` and `true`);
+    /// - `NSLOG_TLS_VERIFY=1` verifies the certificate of the desktop viewer, against the system
+    ///   certificates and those of the `NSLOG_TLS_CA_FILE` PEM file, which enables the
+    ///   verification;
+    /// - `NSLOG_MAX_FILE_SIZE` rotates the log file past the given number of bytes, keeping
+    ///   `NSLOG_MAX_FILES` rotated files (1 by default);
+    /// - `NSLOG_LEVEL` accepts a single level, or `env_logger`-style directives, see
+    ///   [`TargetFilter`];
+    /// - `NSLOG_DOMAINS` adds prefix rules, e.g. `my_crate::db=DB,my_crate::net=Network`;
+    /// - `NSLOG_DOMAIN_DEPTH` truncates the module paths of the unmapped targets;
+    /// - `NSLOG_DEFAULT_DOMAIN` is the domain of the unmapped targets.
+    ///
+    /// Invalid values are reported instead of ignored.
+    pub fn with_env(mut self) -> Result<Self, Error> {
+        let invalid = |name: &str, value: &str| {
+            Error::InvalidConfiguration(format!("invalid value of {name}: {value}"))
+        };
+        if let Ok(filename) = env::var("NSLOG_FILENAME") {
+            self.filename = Some(PathBuf::from(filename));
+            self.host = None;
+            self.bonjour_service = None;
+        } else if let Ok(host) = env::var("NSLOG_HOST") {
+            self.filename = None;
+            self.host = Some(host);
+            self.bonjour_service = None;
+        } else if let Ok(service) = env::var("NSLOG_BONJOUR_SERVICE") {
+            self.filename = None;
+            self.host = None;
+            self.bonjour_service = Some(service);
+        }
+        let parse_bool = |name: &str, value: String| match value.as_str() {
+            "1" | "true" => Ok(true),
+            "0" | "false" => Ok(false),
+            _ => Err(invalid(name, &value)),
+        };
+        if let Ok(use_ssl) = env::var("NSLOG_USE_SSL") {
+            self.use_ssl = Some(parse_bool("NSLOG_USE_SSL", use_ssl)?);
+        }
+        if let Ok(verify) = env::var("NSLOG_TLS_VERIFY") {
+            self.tls_verify = Some(parse_bool("NSLOG_TLS_VERIFY", verify)?);
+        }
+        if let Ok(ca_file) = env::var("NSLOG_TLS_CA_FILE") {
+            self.tls_ca_file = Some(PathBuf::from(ca_file));
+        }
+        if let Ok(flush) = env::var("NSLOG_FLUSH") {
+            self.flush = Some(parse_bool("NSLOG_FLUSH", flush)?);
+        }
+        if let Ok(max_size) = env::var("NSLOG_MAX_FILE_SIZE") {
+            self.max_file_size = Some(
+                u64::from_str(&max_size).map_err(|_| invalid("NSLOG_MAX_FILE_SIZE", &max_size))?,
+            );
+        }
+        if let Ok(max_files) = env::var("NSLOG_MAX_FILES") {
+            self.max_files = Some(
+                usize::from_str(&max_files).map_err(|_| invalid("NSLOG_MAX_FILES", &max_files))?,
+            );
+        }
+        if let Ok(directives) = env::var("NSLOG_LEVEL") {
+            self.level = Some(TargetFilter::from_str(&directives)?);
+        }
+        if let Ok(rules) = env::var("NSLOG_DOMAINS") {
+            for rule in rules.split(',').filter(|rule| !rule.trim().is_empty()) {
+                let (prefix, domain) = rule
+                    .split_once('=')
+                    .map(|(prefix, domain)| (prefix.trim(), domain.trim()))
+                    .filter(|(prefix, domain)| !prefix.is_empty() && !domain.is_empty())
+                    .ok_or_else(|| invalid("NSLOG_DOMAINS", rule))?;
+                self.domains = self.domains.rule(prefix, Domain::from_str(domain).unwrap());
+            }
+        }
+        if let Ok(depth) = env::var("NSLOG_DOMAIN_DEPTH") {
+            let depth =
+                usize::from_str(&depth).map_err(|_| invalid("NSLOG_DOMAIN_DEPTH", &depth))?;
+            self.domains = self.domains.depth(depth);
+        }
+        if let Ok(domain) = env::var("NSLOG_DEFAULT_DOMAIN") {
+            self.domains = self
+                .domains
+                .default_domain(Domain::from_str(&domain).unwrap());
+        }
+        Ok(self)
+    }
+
+    /// Destination of the messages: the file, the remote host, or the Bonjour service, in this
+    /// order. SSL is enabled by default, except for files.
+    pub(crate) fn connection_mode(&self) -> Result<ConnectionMode, Error> {
+        let use_ssl = self.use_ssl.unwrap_or(true);
+        if let Some(filename) = &self.filename {
+            return Ok(ConnectionMode::File(filename.clone()));
+        }
+        if let Some(host) = &self.host {
+            let (host_name, port) = host
+                .rsplit_once(':')
+                .and_then(|(host_name, port)| Some((host_name, u16::from_str(port).ok()?)))
+                .filter(|(host_name, _)| !host_name.is_empty())
+                .ok_or_else(|| {
+                    Error::InvalidConfiguration(format!("invalid host {host}, expected host:port"))
+                })?;
+            return Ok(ConnectionMode::Tcp(host_name.to_string(), port, use_ssl));
+        }
+        Ok(ConnectionMode::Bonjour(match &self.bonjour_service {
+            Some(service) => BonjourServiceType::Custom(service.clone(), use_ssl),
+            None => BonjourServiceType::Default(use_ssl),
+        }))
+    }
+
+    /// Builder of a logger with these options, validated when building it. Like `use_ssl`, the
+    /// TLS options are ignored when logging to a file, e.g. selected with `NSLOG_FILENAME`, and the
+    /// rotation options otherwise.
+    pub(crate) fn builder(self) -> Result<LoggerBuilder, Error> {
+        let connection_mode = self.connection_mode()?;
+        let logs_to_file = matches!(connection_mode, ConnectionMode::File(_));
+        let mut builder = LoggerBuilder::new()
+            .connection_mode(connection_mode)
+            .filter(self.level.unwrap_or_default())
+            .flush_messages(self.flush.unwrap_or_default())
+            .domain_mapping(self.domains);
+        if let Some(verify) = self.tls_verify.filter(|_| !logs_to_file) {
+            builder = builder.tls_verify(verify);
+        }
+        if let Some(ca_file) = self.tls_ca_file.filter(|_| !logs_to_file) {
+            builder = builder.tls_ca_file(ca_file);
+        }
+        match (self.max_file_size, self.max_files) {
+            (Some(max_size), max_files) if logs_to_file => {
+                builder = builder.rotate_log_file(max_size, max_files.unwrap_or(1));
+            }
+            (None, Some(_)) => {
+                return Err(Error::InvalidConfiguration(
+                    "max_files requires max_file_size".to_string(),
+                ));
+            }
+            _ => {}
+        }
+        if let Some(max_queued_messages) = self.max_queued_messages {
+            builder = builder.max_queued_messages(max_queued_messages);
+        }
+        if let Some(name) = &self.client_name {
+            builder = builder.client_name(name);
+        }
+        if let Some(version) = &self.client_version {
+            builder = builder.client_version(version);
+        }
+        if let Some(key_value_parts) = self.key_value_parts {
+            builder = builder.key_value_parts(key_value_parts);
+        }
+        if let Some(style) = self.key_value_text {
+            builder = builder.key_value_text(style);
+        }
+        Ok(builder)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serial_test::serial;
+
+    use super::*;
+
+    fn parse_env() -> (TargetFilter, ConnectionMode, bool) {
+        let config = LoggerConfig::default()
+            .with_env()
+            .expect("valid environment");
+        (
+            config.level.clone().unwrap_or_default(),
+            config.connection_mode().expect("valid connection mode"),
+            config.flush.unwrap_or_default(),
+        )
+    }
+
+    fn parse_domain_env() -> DomainMapping {
+        LoggerConfig::default()
+            .with_env()
+            .expect("valid environment")
+            .domains
+    }
+
+    #[test]
+    #[serial]
+    fn parses_default_env() {
+        assert_eq!(
+            (
+                log::LevelFilter::Warn.into(),
+                ConnectionMode::default(),
+                false
+            ),
+            parse_env()
+        )
+    }
+
+    #[test]
+    #[serial]
+    fn parses_file_path_from_env() {
+        unsafe {
+            env::set_var("NSLOG_FILENAME", "/tmp/file_output.log");
+        }
+        assert_eq!(
+            (
+                log::LevelFilter::Warn.into(),
+                ConnectionMode::File(PathBuf::from("/tmp/file_output.log")),
+                false
+            ),
+            parse_env()
+        );
+        unsafe {
+            env::remove_var("NSLOG_FILENAME");
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn parses_log_level_from_env() {
+        unsafe {
+            env::set_var("NSLOG_LEVEL", "INFO");
+        }
+        assert_eq!(
+            (
+                log::LevelFilter::Info.into(),
+                ConnectionMode::default(),
+                false
+            ),
+            parse_env()
+        );
+        unsafe {
+            env::remove_var("NSLOG_LEVEL");
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn disables_bonjour_ssl_from_env() {
+        unsafe {
+            env::set_var("NSLOG_USE_SSL", "0");
+        }
+        assert_eq!(
+            (
+                log::LevelFilter::Warn.into(),
+                ConnectionMode::Bonjour(BonjourServiceType::Default(false)),
+                false
+            ),
+            parse_env()
+        );
+        unsafe {
+            env::remove_var("NSLOG_USE_SSL");
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn sets_remote_host_from_env() {
+        unsafe {
+            env::set_var("NSLOG_HOST", "127.0.0.1:50000");
+        }
+        assert_eq!(
+            (
+                log::LevelFilter::Warn.into(),
+                ConnectionMode::Tcp("127.0.0.1".to_string(), 50000, true),
+                false
+            ),
+            parse_env()
+        );
+        unsafe {
+            env::remove_var("NSLOG_HOST");
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn sets_message_flushing_from_env() {
+        unsafe {
+            env::set_var("NSLOG_FLUSH", "1");
+        }
+        assert_eq!(
+            (
+                log::LevelFilter::Warn.into(),
+                ConnectionMode::default(),
+                true
+            ),
+            parse_env()
+        );
+        unsafe {
+            env::remove_var("NSLOG_FLUSH");
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn parses_domain_env() {
+        assert_eq!(DomainMapping::new(), parse_domain_env());
+
+        unsafe {
+            env::set_var("NSLOG_DOMAINS", "my_crate::db=DB, my_crate::net=Network");
+            env::set_var("NSLOG_DOMAIN_DEPTH", "2");
+            env::set_var("NSLOG_DEFAULT_DOMAIN", "App");
+        }
+        assert_eq!(
+            DomainMapping::new()
+                .rule("my_crate::db", Domain::DB)
+                .rule("my_crate::net", Domain::Network)
+                .depth(2)
+                .default_domain(Domain::App),
+            parse_domain_env()
+        );
+        unsafe {
+            env::remove_var("NSLOG_DOMAINS");
+            env::remove_var("NSLOG_DOMAIN_DEPTH");
+            env::remove_var("NSLOG_DEFAULT_DOMAIN");
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn parses_rotation_from_env() {
+        unsafe {
+            env::set_var("NSLOG_FILENAME", "/tmp/file_output.log");
+            env::set_var("NSLOG_MAX_FILE_SIZE", "1048576");
+            env::set_var("NSLOG_MAX_FILES", "3");
+        }
+        let config = LoggerConfig::default().with_env();
+        unsafe {
+            env::remove_var("NSLOG_FILENAME");
+            env::remove_var("NSLOG_MAX_FILE_SIZE");
+            env::remove_var("NSLOG_MAX_FILES");
+        }
+        let config = config.expect("valid environment");
+        assert_eq!(Some(1048576), config.max_file_size);
+        assert_eq!(Some(3), config.max_files);
+        assert!(config.builder().is_ok());
+
+        let config = LoggerConfig {
+            filename: Some(PathBuf::from("/tmp/file_output.log")),
+            max_files: Some(3),
+            ..LoggerConfig::default()
+        };
+        assert!(matches!(
+            config.builder(),
+            Err(Error::InvalidConfiguration(_))
+        ));
+    }
+
+    #[test]
+    #[serial]
+    fn reports_invalid_env() {
+        for (name, value) in [
+            ("NSLOG_HOST", "127.0.0.1"),
+            ("NSLOG_HOST", "127.0.0.1:port"),
+            ("NSLOG_USE_SSL", "no"),
+            ("NSLOG_FLUSH", "yes"),
+            ("NSLOG_TLS_VERIFY", "on"),
+            ("NSLOG_MAX_FILE_SIZE", "1MB"),
+            ("NSLOG_MAX_FILES", "-1"),
+            ("NSLOG_LEVEL", "my_crate=verbose"),
+            ("NSLOG_DOMAINS", "my_crate::db=DB,invalid"),
+            ("NSLOG_DOMAIN_DEPTH", "two"),
+        ] {
+            unsafe {
+                env::set_var(name, value);
+            }
+            let result = LoggerConfig::default()
+                .with_env()
+                .and_then(|config| config.connection_mode());
+            unsafe {
+                env::remove_var(name);
+            }
+            assert!(
+                matches!(result, Err(Error::InvalidConfiguration(_))),
+                "{name}={value}"
+            );
+        }
+    }
+
+    #[cfg(feature = "config")]
+    #[test]
+    #[serial]
+    fn overrides_file_with_env() {
+        use std::io::Write;
+
+        let mut file = tempfile::NamedTempFile::new().expect("temp file");
+        writeln!(
+            file,
+            r#"
+host = "192.168.1.10:50000"
+use_ssl = true
+tls_verify = true
+tls_ca_file = "/etc/nslogger/viewer.pem"
+level = "info,my_crate::db=trace"
+max_queued_messages = 100
+key_value_text = "logfmt"
+
+[domains]
+rules = {{ "my_crate::db" = "DB" }}
+depth = 2
+"#
+        )
+        .expect("config written");
+        let config = LoggerConfig::from_file(file.path()).expect("valid config");
+        assert_eq!(
+            ConnectionMode::Tcp("192.168.1.10".to_string(), 50000, true),
+            config.connection_mode().unwrap()
+        );
+        assert_eq!(Some(true), config.tls_verify);
+        assert_eq!(
+            Some(PathBuf::from("/etc/nslogger/viewer.pem")),
+            config.tls_ca_file
+        );
+        assert_eq!(Some(100), config.max_queued_messages);
+        assert_eq!(Some(KeyValueText::Logfmt), config.key_value_text);
+
+        unsafe {
+            env::set_var("NSLOG_FILENAME", "/tmp/file_output.log");
+            env::set_var("NSLOG_DOMAINS", "my_crate::net=Network");
+        }
+        let config = config.with_env();
+        unsafe {
+            env::remove_var("NSLOG_FILENAME");
+            env::remove_var("NSLOG_DOMAINS");
+        }
+        let config = config.expect("valid environment");
+        assert_eq!(
+            ConnectionMode::File(PathBuf::from("/tmp/file_output.log")),
+            config.connection_mode().unwrap()
+        );
+        assert_eq!(
+            Some("info,my_crate::db=trace".parse().unwrap()),
+            config.level
+        );
+        assert_eq!(
+            DomainMapping::new()
+                .rule("my_crate::db", Domain::DB)
+                .depth(2)
+                .rule("my_crate::net", Domain::Network),
+            config.domains
+        );
+    }
+
+    #[cfg(feature = "config")]
+    #[test]
+    fn reports_invalid_file() {
+        use std::io::Write;
+
+        for contents in [
+            "level = \"my_crate=verbose\"",
+            "port = 50000",
+            "use_ssl = \"no\"",
+        ] {
+            let mut file = tempfile::NamedTempFile::new().expect("temp file");
+            writeln!(file, "{contents}").expect("config written");
+            assert!(
+                matches!(
+                    LoggerConfig::from_file(file.path()),
+                    Err(Error::InvalidConfiguration(_))
+                ),
+                "{contents}"
+            );
+        }
+        assert!(matches!(
+            LoggerConfig::from_file(Path::new("/nonexistent/nslogger.toml")),
+            Err(Error::InvalidConfiguration(_))
+        ));
+    }
+}
@@ -0,0 +1,103 @@
+use std::{
+    cell::RefCell,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use crate::nslogger::{KeyValues, LogMessage};
+
+thread_local! {
+    /// Contexts of the enclosing scopes, each one merged with the enclosing contexts.
+    static CONTEXTS: RefCell<Vec<KeyValues>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Runs `f` with the given key-value pairs attached to every message logged by the current
+/// thread, in addition to the pairs of the enclosing scopes. The innermost pairs take precedence.
+///
+/// The pairs are sent as separate message parts, and prefixed to the message text when the logger
+/// appends the key-value pairs to the text, see [`LoggerBuilder::key_value_text`].
+///
+/// ```rust,no_run
+/// use log::kv::Value;
+///
+/// let context: [(&str, Value); 2] = [("request_id", 42.into()), ("tenant", "acme".into())];
+/// nslogger::with_context(&context, || {
+///     log::info!("handling request");
+/// });
+/// ```
+///
+/// [`LoggerBuilder::key_value_text`]: crate::LoggerBuilder::key_value_text
+pub fn with_context<R>(context: &dyn log::kv::Source, f: impl FnOnce() -> R) -> R {
+    let context = merged(KeyValues::from_source(context));
+    let _scope = Scope::enter(context);
+    f()
+}
+
+/// Same as [`with_context`], for the messages logged while polling the given future, whichever
+/// thread polls it. The future also keeps the context of the current scope, e.g. when spawned.
+///
+/// ```rust,no_run
+/// # async fn handle_request() {}
+/// # async fn example() {
+/// let context = [("request_id", 42)];
+/// nslogger::with_context_async(&context, handle_request()).await;
+/// # }
+/// ```
+pub fn with_context_async<F: Future>(context: &dyn log::kv::Source, future: F) -> WithContext<F> {
+    WithContext {
+        context: merged(KeyValues::from_source(context)),
+        future: Box::pin(future),
+    }
+}
+
+/// Future returned by [`with_context_async`].
+pub struct WithContext<F> {
+    context: KeyValues,
+    future: Pin<Box<F>>,
+}
+
+impl<F: Future> Future for WithContext<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        let _scope = Scope::enter(self.context.clone());
+        self.future.as_mut().poll(cx)
+    }
+}
+
+/// Adds the context of the current scope to the message, as separate parts.
+pub(crate) fn add_parts(log_message: &mut LogMessage) {
+    CONTEXTS.with_borrow(|contexts| {
+        if let Some(context) = contexts.last() {
+            context.add_parts(log_message);
+        }
+    });
+}
+
+/// Context of the current scope.
+pub(crate) fn current() -> KeyValues {
+    CONTEXTS.with_borrow(|contexts| contexts.last().cloned().unwrap_or_default())
+}
+
+fn merged(context: KeyValues) -> KeyValues {
+    let mut merged = current();
+    merged.extend(&context);
+    merged
+}
+
+/// Removes the context of a scope when it ends, including by unwinding.
+struct Scope;
+
+impl Scope {
+    fn enter(context: KeyValues) -> Self {
+        CONTEXTS.with_borrow_mut(|contexts| contexts.push(context));
+        Scope
+    }
+}
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        CONTEXTS.with_borrow_mut(|contexts| contexts.pop());
+    }
+}
@@ -0,0 +1,169 @@
+use std::str::FromStr;
+
+use crate::nslogger::{filter::matches_target, Domain, Error};
+
+/// Maps the targets of the records sent through the `log` facade to NSLogger domains.
+///
+/// A target is mapped, in order:
+/// - to the domain of the longest matching prefix rule, if any;
+/// - to the default domain, if set;
+/// - to a custom domain named after the target, truncated to its first path segments when a
+///   depth is set.
+///
+/// With the `serde` feature, the rules are serialized as a map from the target prefixes to the
+/// domains, e.g. `{ rules = { "my_crate::db" = "DB" }, depth = 2 }` in TOML.
+///
+/// ```rust
+/// use nslogger::{Domain, DomainMapping};
+///
+/// let mapping = DomainMapping::new()
+///     .rule("my_crate::db", Domain::DB)
+///     .depth(2);
+/// assert_eq!(Domain::DB, mapping.domain_for("my_crate::db::pool"));
+/// assert_eq!(
+///     Domain::Custom("my_crate::net".to_string()),
+///     mapping.domain_for("my_crate::net::http")
+/// );
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default, deny_unknown_fields))]
+pub struct DomainMapping {
+    #[cfg_attr(feature = "serde", serde(with = "rules"))]
+    rules: Vec<(String, Domain)>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    depth: Option<usize>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    default: Option<Domain>,
+}
+
+impl DomainMapping {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Maps the targets starting with `target_prefix` to the given domain, replacing any previous
+    /// rule for the same prefix.
+    pub fn rule(mut self, target_prefix: &str, domain: Domain) -> Self {
+        self.rules.retain(|(prefix, _)| prefix != target_prefix);
+        self.rules.push((target_prefix.to_string(), domain));
+        self
+    }
+
+    /// Keeps only the first `depth` segments of the module paths of the unmapped targets, e.g.
+    /// `my_crate::net` for `my_crate::net::http` with a depth of 2.
+    pub fn depth(mut self, depth: usize) -> Self {
+        self.depth = Some(depth);
+        self
+    }
+
+    /// Domain of the targets that don't match any rule, instead of a domain named after them.
+    pub fn default_domain(mut self, domain: Domain) -> Self {
+        self.default = Some(domain);
+        self
+    }
+
+    /// Domain of the records with the given target.
+    pub fn domain_for(&self, target: &str) -> Domain {
+        if let Some((_, domain)) = self
+            .rules
+            .iter()
+            .filter(|(prefix, _)| matches_target(target, prefix))
+            .max_by_key(|(prefix, _)| prefix.len())
+        {
+            return domain.clone();
+        }
+        if let Some(domain) = &self.default {
+            return domain.clone();
+        }
+        let target = match self.depth {
+            Some(depth) => target
+                .match_indices("::")
+                .nth(depth.saturating_sub(1))
+                .map_or(target, |(index, _)| &target[..index]),
+            None => target,
+        };
+        Domain::from_str(target).unwrap()
+    }
+
+    pub(crate) fn validate(&self) -> Result<(), Error> {
+        let invalid = |reason: &str| Err(Error::InvalidConfiguration(reason.to_string()));
+        if self.rules.iter().any(|(prefix, _)| prefix.is_empty()) {
+            return invalid("target prefix of a domain mapping is empty");
+        }
+        if self.depth == Some(0) {
+            return invalid("domain depth can't be 0");
+        }
+        Ok(())
+    }
+}
+
+/// (De)serializes the rules as a map, keeping their order.
+#[cfg(feature = "serde")]
+mod rules {
+    use std::fmt;
+
+    use serde::{
+        de::{MapAccess, Visitor},
+        Deserializer, Serializer,
+    };
+
+    use crate::nslogger::Domain;
+
+    pub fn serialize<S: Serializer>(
+        rules: &[(String, Domain)],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(rules.iter().map(|(prefix, domain)| (prefix, domain)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<(String, Domain)>, D::Error> {
+        struct RulesVisitor;
+
+        impl<'de> Visitor<'de> for RulesVisitor {
+            type Value = Vec<(String, Domain)>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a map from target prefixes to domains")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut rules = Vec::new();
+                while let Some(rule) = map.next_entry()? {
+                    rules.push(rule);
+                }
+                Ok(rules)
+            }
+        }
+
+        deserializer.deserialize_map(RulesVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_targets() {
+        let mapping = DomainMapping::new()
+            .rule("my_crate", Domain::App)
+            .rule("my_crate::db", Domain::DB);
+        assert_eq!(Domain::DB, mapping.domain_for("my_crate::db::pool"));
+        assert_eq!(Domain::App, mapping.domain_for("my_crate::dbx"));
+        assert_eq!(
+            Domain::Custom("hyper::client".to_string()),
+            mapping.domain_for("hyper::client")
+        );
+        let mapping = mapping.depth(1);
+        assert_eq!(
+            Domain::Custom("hyper".to_string()),
+            mapping.domain_for("hyper::client")
+        );
+        let mapping = mapping.default_domain(Domain::Network);
+        assert_eq!(Domain::Network, mapping.domain_for("hyper::client"));
+        assert_eq!(Domain::DB, mapping.domain_for("my_crate::db"));
+    }
+}
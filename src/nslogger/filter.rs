@@ -0,0 +1,162 @@
+use std::{fmt, str::FromStr};
+
+use crate::nslogger::Error;
+
+/// Maximum levels of the records sent through the `log` facade, by target prefix.
+///
+/// Parsed from `env_logger`-style directives, e.g. `warn,my_crate::db=trace,hyper=off`: a bare
+/// level sets the default, `prefix=level` sets the level of the targets starting with `prefix`,
+/// and a bare `prefix` enables all the levels for them. The longest matching prefix wins.
+///
+/// With the `serde` feature, filters are serialized as their directives.
+///
+/// ```rust
+/// use nslogger::TargetFilter;
+///
+/// let filter: TargetFilter = "warn,my_crate::db=trace,hyper=off".parse().unwrap();
+/// assert_eq!(log::LevelFilter::Trace, filter.level_for("my_crate::db::pool"));
+/// assert_eq!(log::LevelFilter::Warn, filter.level_for("my_crate::dbx"));
+/// assert_eq!(log::LevelFilter::Off, filter.level_for("hyper"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TargetFilter {
+    default: log::LevelFilter,
+    directives: Vec<(String, log::LevelFilter)>,
+}
+
+impl Default for TargetFilter {
+    fn default() -> Self {
+        log::LevelFilter::Warn.into()
+    }
+}
+
+impl From<log::LevelFilter> for TargetFilter {
+    fn from(default: log::LevelFilter) -> Self {
+        Self {
+            default,
+            directives: Vec::new(),
+        }
+    }
+}
+
+impl TargetFilter {
+    /// Sets the maximum level of the targets starting with `target_prefix`, replacing any
+    /// previous directive for the same prefix.
+    pub fn directive(mut self, target_prefix: &str, level: log::LevelFilter) -> Self {
+        self.directives
+            .retain(|(prefix, _)| prefix != target_prefix);
+        self.directives.push((target_prefix.to_string(), level));
+        self
+    }
+
+    /// Maximum level of the records with the given target.
+    pub fn level_for(&self, target: &str) -> log::LevelFilter {
+        self.directives
+            .iter()
+            .filter(|(prefix, _)| matches_target(target, prefix))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, level)| *level)
+            .unwrap_or(self.default)
+    }
+
+    /// Most verbose level enabled for any target, to be given to [`log::set_max_level`].
+    pub fn max_level(&self) -> log::LevelFilter {
+        self.directives
+            .iter()
+            .map(|(_, level)| *level)
+            .fold(self.default, Ord::max)
+    }
+}
+
+impl FromStr for TargetFilter {
+    type Err = Error;
+
+    fn from_str(directives: &str) -> Result<Self, Error> {
+        let invalid = |directive: &str| {
+            Error::InvalidConfiguration(format!("invalid level directive: {directive}"))
+        };
+        directives
+            .split(',')
+            .map(str::trim)
+            .filter(|directive| !directive.is_empty())
+            .try_fold(
+                Self::from(log::LevelFilter::Warn),
+                |filter, directive| match directive.split_once('=') {
+                    Some((prefix, level)) if !prefix.trim().is_empty() => {
+                        let level = log::LevelFilter::from_str(level.trim())
+                            .map_err(|_| invalid(directive))?;
+                        Ok(filter.directive(prefix.trim(), level))
+                    }
+                    Some(_) => Err(invalid(directive)),
+                    None => match log::LevelFilter::from_str(directive) {
+                        Ok(default) => Ok(Self { default, ..filter }),
+                        Err(_) => Ok(filter.directive(directive, log::LevelFilter::Trace)),
+                    },
+                },
+            )
+    }
+}
+
+impl fmt::Display for TargetFilter {
+    /// Formats the filter as directives, which can be parsed back.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let default = self.default.to_string().to_lowercase();
+        f.write_str(&default)?;
+        for (prefix, level) in &self.directives {
+            write!(f, ",{prefix}={}", level.to_string().to_lowercase())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for TargetFilter {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for TargetFilter {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let directives = String::deserialize(deserializer)?;
+        directives.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Whether the target is the given module path, or one of its submodules.
+pub(crate) fn matches_target(target: &str, prefix: &str) -> bool {
+    target
+        .strip_prefix(prefix)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_directives() {
+        let filter: TargetFilter = "info, my_crate::db=trace ,hyper=off".parse().unwrap();
+        assert_eq!(
+            TargetFilter::from(log::LevelFilter::Info)
+                .directive("my_crate::db", log::LevelFilter::Trace)
+                .directive("hyper", log::LevelFilter::Off),
+            filter
+        );
+        assert_eq!(log::LevelFilter::Trace, filter.max_level());
+        assert_eq!(log::LevelFilter::Info, filter.level_for("my_crate"));
+        assert_eq!(log::LevelFilter::Off, filter.level_for("hyper::client"));
+        assert_eq!("info,my_crate::db=trace,hyper=off", filter.to_string());
+        assert!("my_crate=loud".parse::<TargetFilter>().is_err());
+        assert!("=info".parse::<TargetFilter>().is_err());
+
+        let filter: TargetFilter = "info,my_crate".parse().unwrap();
+        assert_eq!(
+            TargetFilter::from(log::LevelFilter::Info)
+                .directive("my_crate", log::LevelFilter::Trace),
+            filter
+        );
+        assert_eq!(log::LevelFilter::Trace, filter.level_for("my_crate::db"));
+    }
+}
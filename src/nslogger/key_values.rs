@@ -0,0 +1,225 @@
+use std::fmt::Write;
+
+use log::kv::{self, VisitSource};
+
+use crate::nslogger::{LogMessage, MessagePartKey};
+
+/// How the key-value pairs attached to the records are appended to the message text.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+#[non_exhaustive]
+pub enum KeyValueText {
+    /// The pairs are only sent as separate message parts, if enabled.
+    #[default]
+    Omitted,
+    /// `message key=value other_key="quoted value"`
+    Logfmt,
+    /// `message {"key":value,"other_key":"quoted value"}`
+    Json,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Value {
+    Int(i64),
+    UInt(u64),
+    Float(f64),
+    Bool(bool),
+    Str(String),
+}
+
+impl From<kv::Value<'_>> for Value {
+    fn from(value: kv::Value) -> Self {
+        if let Some(value) = value.to_i64() {
+            Self::Int(value)
+        } else if let Some(value) = value.to_u64() {
+            Self::UInt(value)
+        } else if let Some(value) = value.to_bool() {
+            Self::Bool(value)
+        } else if let Some(value) = value.to_f64() {
+            Self::Float(value)
+        } else {
+            Self::Str(value.to_string())
+        }
+    }
+}
+
+/// Key-value pairs attached to a record, in order.
+#[derive(Debug, Clone, Default)]
+pub struct KeyValues(Vec<(String, Value)>);
+
+impl<'kvs> VisitSource<'kvs> for KeyValues {
+    fn visit_pair(&mut self, key: kv::Key<'kvs>, value: kv::Value<'kvs>) -> Result<(), kv::Error> {
+        self.insert(key.as_str(), value.into());
+        Ok(())
+    }
+}
+
+impl KeyValues {
+    pub fn from_record(record: &log::Record) -> Self {
+        Self::from_source(record.key_values())
+    }
+
+    pub fn from_source(source: &dyn kv::Source) -> Self {
+        let mut key_values = Self::default();
+        let _ = source.visit(&mut key_values);
+        key_values
+    }
+
+    /// Adds a pair, replacing the value of a previous pair with the same key.
+    pub(crate) fn insert(&mut self, key: &str, value: Value) {
+        match self.0.iter_mut().find(|(existing, _)| existing == key) {
+            Some((_, existing)) => *existing = value,
+            None => self.0.push((key.to_string(), value)),
+        }
+    }
+
+    /// Adds the pairs of `other`, which take precedence over the pairs with the same keys.
+    pub(crate) fn extend(&mut self, other: &KeyValues) {
+        for (key, value) in &other.0 {
+            self.insert(key, value.clone());
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Adds each pair as a name part followed by a value part, keeping the integers typed.
+    pub fn add_parts(&self, log_message: &mut LogMessage) {
+        for (key, value) in &self.0 {
+            log_message.add_string(MessagePartKey::KeyValueName, key);
+            match value {
+                Value::Int(value) => log_message.add_int64(MessagePartKey::KeyValue, *value as u64),
+                Value::UInt(value) => log_message.add_int64(MessagePartKey::KeyValue, *value),
+                Value::Float(value) => {
+                    log_message.add_string(MessagePartKey::KeyValue, &value.to_string())
+                }
+                Value::Bool(value) => {
+                    log_message.add_string(MessagePartKey::KeyValue, &value.to_string())
+                }
+                Value::Str(value) => log_message.add_string(MessagePartKey::KeyValue, value),
+            }
+        }
+    }
+
+    /// Appends the pairs to the message text, in the given style.
+    pub fn append_text(&self, message: &mut String, style: KeyValueText) {
+        match style {
+            KeyValueText::Omitted => {}
+            KeyValueText::Logfmt => {
+                for (key, value) in &self.0 {
+                    let _ = write!(message, " {key}=");
+                    match value {
+                        Value::Str(value)
+                            if value.is_empty()
+                                || value.contains(|c: char| {
+                                    c.is_whitespace() || c == '=' || c == '"'
+                                }) =>
+                        {
+                            write_quoted(message, value)
+                        }
+                        value => write_scalar(message, value),
+                    }
+                }
+            }
+            KeyValueText::Json => {
+                message.push_str(" {");
+                for (index, (key, value)) in self.0.iter().enumerate() {
+                    if index > 0 {
+                        message.push(',');
+                    }
+                    write_quoted(message, key);
+                    message.push(':');
+                    match value {
+                        Value::Str(value) => write_quoted(message, value),
+                        Value::Float(value) if !value.is_finite() => {
+                            write_quoted(message, &value.to_string())
+                        }
+                        value => write_scalar(message, value),
+                    }
+                }
+                message.push('}');
+            }
+        }
+    }
+
+    /// Prefixes the message text with the pairs, in the given style, e.g.
+    /// `[key=value] message` for logfmt.
+    pub fn prepend_text(&self, message: &mut String, style: KeyValueText) {
+        let mut prefix = String::new();
+        self.append_text(&mut prefix, style);
+        let Some(prefix) = prefix.strip_prefix(' ') else {
+            return;
+        };
+        match style {
+            KeyValueText::Logfmt => message.insert_str(0, &format!("[{prefix}] ")),
+            _ => message.insert_str(0, &format!("{prefix} ")),
+        }
+    }
+}
+
+fn write_scalar(message: &mut String, value: &Value) {
+    let _ = match value {
+        Value::Int(value) => write!(message, "{value}"),
+        Value::UInt(value) => write!(message, "{value}"),
+        Value::Float(value) => write!(message, "{value}"),
+        Value::Bool(value) => write!(message, "{value}"),
+        Value::Str(value) => write!(message, "{value}"),
+    };
+}
+
+/// Writes the string between double quotes, escaped the same way in logfmt and JSON.
+fn write_quoted(message: &mut String, value: &str) {
+    message.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => message.push_str("\\\""),
+            '\\' => message.push_str("\\\\"),
+            '\n' => message.push_str("\\n"),
+            '\r' => message.push_str("\\r"),
+            '\t' => message.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(message, "\\u{:04x}", c as u32);
+            }
+            c => message.push(c),
+        }
+    }
+    message.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key_values() -> KeyValues {
+        let pairs: [(&str, kv::Value); 4] = [
+            ("count", 3.into()),
+            ("ok", true.into()),
+            ("user", "jane doe".into()),
+            ("ratio", 0.5.into()),
+        ];
+        let record = log::Record::builder()
+            .args(format_args!("message"))
+            .key_values(&pairs)
+            .build();
+        KeyValues::from_record(&record)
+    }
+
+    #[test]
+    fn appends_text() {
+        let mut message = "message".to_string();
+        key_values().append_text(&mut message, KeyValueText::Logfmt);
+        assert_eq!(
+            r#"message count=3 ok=true user="jane doe" ratio=0.5"#,
+            message
+        );
+
+        let mut message = "message".to_string();
+        key_values().append_text(&mut message, KeyValueText::Json);
+        assert_eq!(
+            r#"message {"count":3,"ok":true,"user":"jane doe","ratio":0.5}"#,
+            message
+        );
+    }
+}
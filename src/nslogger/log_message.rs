@@ -1,12 +1,14 @@
 use std::{env, ffi::OsStr, fmt, path::Path, str::FromStr, thread, time};
 
 use byteorder::{BigEndian, WriteBytesExt};
-use log;
-use sys_info;
 
 pub const SEQUENCE_NB_OFFSET: usize = 14;
 
-#[derive(Debug, PartialEq)]
+/// Domain of a message, by which the messages can be filtered in the desktop viewer.
+///
+/// With the `serde` feature, domains are serialized as their names, e.g. `"DB"` or
+/// `"my_crate::net"`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum Domain {
     App,
     View,
@@ -53,6 +55,21 @@ impl FromStr for Domain {
     }
 }
 
+#[cfg(feature = "serde")]
+impl serde::Serialize for Domain {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Domain {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Ok(Domain::from_str(&name).unwrap())
+    }
+}
+
 #[derive(Copy, Clone)]
 #[repr(u8)]
 #[allow(dead_code)]
@@ -78,13 +95,22 @@ pub enum MessagePartKey {
 
     // Client info
     ClientName = 20,
-    ClientVersion = 21, // unreachable from Rust
+    ClientVersion = 21,
     OsName = 22,
     OsVersion = 23,
     ClientModel = 24, // Android-specific
     UniqueId = 25,    // Android-specific
 
     UserDefined = 100,
+    /// Raw OS bytes of a file name that isn't valid UTF-8 (the `FileName` part is then lossy).
+    FileNameBytes = 101,
+    /// Raw OS bytes of an executable name that isn't valid UTF-8.
+    ClientNameBytes = 102,
+    /// Key of a key-value pair attached to a record, immediately followed by its value.
+    KeyValueName = 103,
+    /// Value of a key-value pair attached to a record: an `Int64` part for integers, and a
+    /// `String` part otherwise.
+    KeyValue = 104,
 }
 
 #[derive(Copy, Clone)]
@@ -98,7 +124,7 @@ pub(crate) enum MessagePartType {
     Image = 5, // An image, stored in PNG format
 }
 
-#[derive(Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[repr(u32)]
 #[allow(dead_code)]
 pub(crate) enum LogMessageType {
@@ -110,8 +136,17 @@ pub(crate) enum LogMessageType {
     Mark,       // Pseudo-message that defines a "mark" that users can place in the log flow
 }
 
+/// Client details sent to the desktop viewer at the start of each connection.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClientInfo {
+    /// Defaults to the name of the executable.
+    pub name: Option<String>,
+    pub version: Option<String>,
+}
+
 #[derive(Debug)]
 pub struct LogMessage {
+    pub message_type: LogMessageType,
     pub sequence_number: u32,
     pub data: Vec<u8>,
     part_count: u16,
@@ -120,6 +155,7 @@ pub struct LogMessage {
 impl Default for LogMessage {
     fn default() -> Self {
         Self {
+            message_type: LogMessageType::Log,
             sequence_number: 0,
             part_count: 0,
             data: Vec::with_capacity(512),
@@ -128,7 +164,7 @@ impl Default for LogMessage {
 }
 
 impl LogMessage {
-    pub fn client_info() -> LogMessage {
+    pub fn client_info(client_info: &ClientInfo) -> LogMessage {
         let mut message = LogMessage::new(LogMessageType::ClientInfo);
 
         if let Ok(os_type) = sys_info::os_type() {
@@ -137,23 +173,27 @@ impl LogMessage {
         if let Ok(os_release) = sys_info::os_release() {
             message.add_string(MessagePartKey::OsVersion, &os_release);
         }
-        let process_name = env::current_exe()
-            .ok()
-            .as_ref()
-            .map(Path::new)
-            .and_then(Path::file_name)
-            .and_then(OsStr::to_str)
-            .map(String::from);
-
-        if let Some(name) = process_name {
-            message.add_string(MessagePartKey::ClientName, &name);
+        if let Some(name) = &client_info.name {
+            message.add_string(MessagePartKey::ClientName, name);
+        } else if let Some(name) = env::current_exe().ok().as_deref().and_then(Path::file_name) {
+            message.add_os_string(
+                MessagePartKey::ClientName,
+                MessagePartKey::ClientNameBytes,
+                name,
+            );
+        }
+        if let Some(version) = &client_info.version {
+            message.add_string(MessagePartKey::ClientVersion, version);
         }
 
         message
     }
 
     pub fn new(message_type: LogMessageType) -> LogMessage {
-        let mut new_message = LogMessage::default();
+        let mut new_message = LogMessage {
+            message_type,
+            ..LogMessage::default()
+        };
         /*
          * Reserve 6 bytes for the message header.
          */
@@ -181,9 +221,10 @@ impl LogMessage {
         new_message.add_int16(MessagePartKey::Level, level as u16);
 
         if let Some(path) = filename {
-            new_message.add_string(
+            new_message.add_os_string(
                 MessagePartKey::FileName,
-                path.to_str().expect("Invalid path encoding"),
+                MessagePartKey::FileNameBytes,
+                path.as_os_str(),
             );
 
             if let Some(nb) = line_number {
@@ -246,6 +287,23 @@ impl LogMessage {
         self.add_bytes(key, MessagePartType::String, string.as_bytes());
     }
 
+    /// Adds the lossy UTF-8 conversion of the string and, if the conversion isn't exact, the raw OS
+    /// bytes as a binary part so that the original value may be recovered.
+    pub fn add_os_string(
+        &mut self,
+        key: MessagePartKey,
+        bytes_key: MessagePartKey,
+        string: &OsStr,
+    ) {
+        match string.to_str() {
+            Some(string) => self.add_string(key, string),
+            None => {
+                self.add_string(key, &string.to_string_lossy());
+                self.add_binary_data(bytes_key, string.as_encoded_bytes());
+            }
+        }
+    }
+
     fn add_timestamp(&mut self, value: Option<u64>) {
         let value = value.unwrap_or_else(|| {
             time::SystemTime::now()
@@ -1,39 +1,102 @@
 use std::{
     collections::VecDeque,
-    fs::File,
+    fs::{self, File},
     io,
     io::{BufWriter, Write},
     net::TcpStream,
     path::{Path, PathBuf},
+    time::{Duration, Instant},
 };
 
 use openssl::{
     self,
     ssl::{SslConnector, SslMethod, SslStream},
 };
+#[cfg(not(feature = "std-thread"))]
 use tokio::sync::mpsc;
+use tokio::sync::watch;
 
 use crate::nslogger::{
-    log_message::{LogMessage, SEQUENCE_NB_OFFSET},
-    network_manager,
-    network_manager::BonjourServiceType,
-    Error, Signal, DEBUG_LOGGER,
+    channel,
+    log_message::{ClientInfo, LogMessage, LogMessageType, SEQUENCE_NB_OFFSET},
+    stats::SharedStats,
+    Error, SharedErrors, Signal, DEBUG_LOGGER,
 };
 
 #[derive(Debug)]
 pub enum Message {
+    #[cfg(not(feature = "std-thread"))]
     ConnectToBonjourService(String, u16, bool),
     AddLog(LogMessage, Option<Signal>),
     SwitchConnection(ConnectionMode),
+    Configure(WorkerConfig),
+    /// Signaled once all the messages received before it have been written and flushed.
+    Flush(Signal),
+    /// Same as `Flush`, after which the connection is closed and the worker stops.
+    Shutdown(Signal),
 }
 
+/// Worker settings that are applied all at once, with a single connection setup.
+#[derive(Debug, Clone, Default)]
+pub struct WorkerConfig {
+    pub connection_mode: ConnectionMode,
+    /// Maximum number of messages kept while waiting for a connection. The oldest messages are
+    /// dropped first.
+    pub max_queued_messages: Option<usize>,
+    pub client_info: ClientInfo,
+    pub tls: TlsOptions,
+    pub rotation: Option<FileRotation>,
+}
+
+/// Size limit of the log files, past which they are rotated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileRotation {
+    /// Maximum size of a log file, in bytes. A single larger message is still written as a whole.
+    pub max_size: u64,
+    /// Number of rotated files kept, e.g. `output.log.1` (the most recent) to `output.log.3`.
+    pub max_files: usize,
+}
+
+/// Verification of the certificate of the desktop viewer, for the SSL connections.
+#[derive(Debug, Clone, Default)]
+pub struct TlsOptions {
+    /// Whether the certificate is verified, along with the host name. The desktop viewer uses a
+    /// self-signed certificate by default, hence it isn't verified unless requested.
+    pub verify: bool,
+    /// PEM file of the certificates trusted in addition to the system ones.
+    pub ca_file: Option<PathBuf>,
+}
+
+/// Destination of the log messages.
+///
+/// The default mode browses the local network for an NSLogger viewer advertising the SSL Bonjour
+/// service.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[non_exhaustive]
 pub enum ConnectionMode {
+    /// Connect directly to a viewer, given its host name, port, and whether to use SSL.
     Tcp(String, u16, bool),
+    /// Look up the viewer through Bonjour.
     Bonjour(BonjourServiceType),
+    /// Write the messages to a file that can later be opened in the viewer.
     File(PathBuf),
 }
 
+/// Bonjour service used to look up the NSLogger viewer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[non_exhaustive]
+pub enum BonjourServiceType {
+    /// Custom service type (e.g. `_mylogger._tcp.`), and whether to use SSL.
+    Custom(String, bool),
+    /// Default NSLogger service type. The flag defines whether to use SSL, and selects between
+    /// the `_nslogger-ssl._tcp.` and `_nslogger._tcp.` service types.
+    Default(bool),
+}
+
 impl Default for ConnectionMode {
     fn default() -> Self {
         Self::Bonjour(BonjourServiceType::Default(true))
@@ -65,31 +128,84 @@ impl std::io::Write for WriteStreamWrapper {
     }
 }
 
-#[derive(Debug, Default, PartialEq, Eq)]
-enum ConnectionState {
+/// State of the connection to the desktop viewer (or to the log file).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ConnectionState {
     #[default]
     Disconnected,
+    /// Looking up the desktop viewer through Bonjour, or connecting to it.
     Connecting,
+    /// Connected, but the client info wasn't sent yet.
     Connected,
+    /// Messages are being written.
     Ready,
 }
 
+/// Connection details reported to the connection state subscribers.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ConnectionStatus {
+    pub state: ConnectionState,
+    /// Host name of the desktop viewer (IP address when found through Bonjour), when connected.
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    /// Whether the connection to the desktop viewer uses SSL.
+    pub tls: bool,
+    /// Last connection or write error, if any.
+    pub last_error: Option<String>,
+}
+
+/// Delay before retrying to connect to the desktop viewer, doubled after each failed attempt.
+const MIN_RECONNECT_DELAY: Duration = Duration::from_millis(500);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
 pub struct LogWorker {
-    message_rx: mpsc::UnboundedReceiver<Message>,
+    message_rx: channel::UnboundedReceiver<Message>,
     ready_signal: Signal,
     sequence_generator: u32,
     connection_state: ConnectionState,
+    status_tx: watch::Sender<ConnectionStatus>,
+    stats: SharedStats,
+    /// Receives the errors, along with `status_tx`.
+    errors: SharedErrors,
+    /// Whether the current destination was reached at least once, in which case any new
+    /// connection attempt is a reconnection.
+    was_connected: bool,
+    /// Time of the next connection attempt, after the desktop viewer couldn't be reached.
+    reconnect_at: Option<Instant>,
+    reconnect_delay: Duration,
     pub connection_mode: ConnectionMode,
     pub write_stream: Option<WriteStreamWrapper>,
     pub log_messages: VecDeque<(LogMessage, Option<Signal>)>,
-    command_tx: mpsc::UnboundedSender<network_manager::BonjourServiceType>,
+    max_queued_messages: Option<usize>,
+    client_info: ClientInfo,
+    tls: TlsOptions,
+    rotation: Option<FileRotation>,
+    /// Number of bytes written to the current log file.
+    file_size: u64,
+    /// Number of log messages received by the worker.
+    enqueued_count: u64,
+    /// Number of log messages either written or dropped.
+    processed_count: u64,
+    /// Flush requests, along with the number of messages that must be processed beforehand.
+    pending_flushes: Vec<(u64, Signal)>,
+    /// Number of messages to process before stopping the worker, once a shutdown was requested.
+    shutdown_target: Option<u64>,
+    /// Signaled once the worker stopped, and doesn't accept messages anymore.
+    shutdown_signals: Vec<Signal>,
+    #[cfg(not(feature = "std-thread"))]
+    command_tx: mpsc::UnboundedSender<BonjourServiceType>,
 }
 
 impl LogWorker {
     pub fn new(
-        command_tx: mpsc::UnboundedSender<network_manager::BonjourServiceType>,
-        message_rx: mpsc::UnboundedReceiver<Message>,
+        #[cfg(not(feature = "std-thread"))] command_tx: mpsc::UnboundedSender<BonjourServiceType>,
+        message_rx: channel::UnboundedReceiver<Message>,
         ready_signal: Signal,
+        status_tx: watch::Sender<ConnectionStatus>,
+        stats: SharedStats,
+        errors: SharedErrors,
     ) -> Self {
         /*
          * NOTE the worker won't process the client info message, hence the very first message
@@ -102,33 +218,144 @@ impl LogWorker {
             connection_mode: ConnectionMode::default(),
             write_stream: None,
             connection_state: ConnectionState::default(),
+            status_tx,
+            stats,
+            errors,
+            was_connected: false,
+            reconnect_at: None,
+            reconnect_delay: MIN_RECONNECT_DELAY,
             log_messages: VecDeque::new(),
+            max_queued_messages: None,
+            client_info: ClientInfo::default(),
+            tls: TlsOptions::default(),
+            rotation: None,
+            file_size: 0,
+            enqueued_count: 0,
+            processed_count: 0,
+            pending_flushes: Vec::new(),
+            shutdown_target: None,
+            shutdown_signals: Vec::new(),
+            #[cfg(not(feature = "std-thread"))]
             command_tx,
         }
     }
 
+    /// Processes the messages until the worker is shut down, or all the loggers are dropped.
+    #[cfg(not(feature = "std-thread"))]
     pub async fn run(&mut self) -> Result<(), Error> {
+        self.start();
+        loop {
+            let message = match self.reconnect_at {
+                Some(at) => {
+                    let deadline = tokio::time::Instant::from_std(at);
+                    match tokio::time::timeout_at(deadline, self.message_rx.recv()).await {
+                        Ok(message) => message,
+                        Err(_) => {
+                            self.retry_connection()?;
+                            if self.shutdown_complete() {
+                                break;
+                            }
+                            continue;
+                        }
+                    }
+                }
+                None => self.message_rx.recv().await,
+            };
+            let Some(message) = message else {
+                break;
+            };
+            if self.process_message(message)? {
+                break;
+            }
+        }
+        self.stop()
+    }
+
+    /// Processes the messages until the worker is shut down, or all the loggers are dropped.
+    #[cfg(feature = "std-thread")]
+    pub fn run(&mut self) -> Result<(), Error> {
+        use std::sync::mpsc::RecvTimeoutError;
+
+        self.start();
+        loop {
+            let message = match self.reconnect_at {
+                Some(at) => match self
+                    .message_rx
+                    .recv_timeout(at.saturating_duration_since(Instant::now()))
+                {
+                    Ok(message) => message,
+                    Err(RecvTimeoutError::Timeout) => {
+                        self.retry_connection()?;
+                        if self.shutdown_complete() {
+                            break;
+                        }
+                        continue;
+                    }
+                    Err(RecvTimeoutError::Disconnected) => break,
+                },
+                None => match self.message_rx.recv() {
+                    Ok(message) => message,
+                    Err(_) => break,
+                },
+            };
+            if self.process_message(message)? {
+                break;
+            }
+        }
+        self.stop()
+    }
+
+    fn start(&mut self) {
         if DEBUG_LOGGER {
             log::info!("logging thread starting up");
         }
 
-        // Since we don't have a straightforward way to block the loop (cf Android), we'll setup
-        // the connection before releasing the waiting thread(s).
-
         /*
-         * Initial setup according to current parameters.
+         * NOTE the connection is only set up once the first message is received, or once the
+         * worker is configured, so that the initial setup isn't immediately torn down by a
+         * configuration change.
+         *
+         * We are ready to run. Unpark the waiting threads now
          */
-        self.setup_connection()?;
+        self.ready_signal.signal();
+
         if DEBUG_LOGGER {
             log::info!("starting log event loop");
         }
+    }
 
-        self.run_loop().await?;
+    /// Handles a message, and returns whether the worker should stop.
+    fn process_message(&mut self, message: Message) -> Result<bool, Error> {
+        if let Err(err) = self.handle_message(message) {
+            self.report_error(&err);
+            return Err(err);
+        }
+        Ok(self.shutdown_complete())
+    }
+
+    /// Whether a shutdown was requested, and all the messages received before it were processed.
+    fn shutdown_complete(&self) -> bool {
+        self.shutdown_target
+            .is_some_and(|target| target <= self.processed_count)
+    }
+
+    fn stop(&mut self) -> Result<(), Error> {
+        let result = self.close_buffer_write_stream();
+        if !self.shutdown_signals.is_empty() {
+            /*
+             * NOTE the channel is closed before signaling, so that the messages logged once the
+             * shutdown completed are reported as discarded.
+             */
+            channel::close(&mut self.message_rx);
+            self.shutdown_signals
+                .drain(..)
+                .for_each(|signal| signal.signal());
+        }
 
         if DEBUG_LOGGER {
             log::info!("stopped log event loop");
         }
-        Ok(())
+        result
     }
 
     fn handle_message(&mut self, message: Message) -> Result<(), Error> {
@@ -151,33 +378,125 @@ impl LogWorker {
                 }
 
                 self.log_messages.push_back((message, signal));
+                self.enqueued_count += 1;
+                self.stats.record_queue_length(self.log_messages.len());
+                self.drop_overflowing_messages();
                 self.process_log_queue()?;
             }
+            Message::Flush(signal) => {
+                self.pending_flushes.push((self.enqueued_count, signal));
+                self.process_log_queue()?;
+                self.complete_flushes()?;
+            }
+            Message::Shutdown(signal) => {
+                if DEBUG_LOGGER {
+                    log::info!("shutdown requested, draining the queue");
+                }
+                self.shutdown_target.get_or_insert(self.enqueued_count);
+                self.shutdown_signals.push(signal);
+                self.process_log_queue()?;
+                self.complete_flushes()?;
+            }
             Message::SwitchConnection(new_mode) => {
                 self.change_options(new_mode)?;
             }
+            Message::Configure(config) => {
+                self.max_queued_messages = config.max_queued_messages;
+                self.client_info = config.client_info;
+                self.tls = config.tls;
+                self.rotation = config.rotation;
+                self.drop_overflowing_messages();
+                self.change_options(config.connection_mode)?;
+                self.process_log_queue()?;
+            }
+            #[cfg(not(feature = "std-thread"))]
+            Message::ConnectToBonjourService(..)
+                if !matches!(self.connection_mode, ConnectionMode::Bonjour(_)) =>
+            {
+                if DEBUG_LOGGER {
+                    log::info!("ignoring Bonjour service found after leaving Bonjour mode");
+                }
+            }
+            #[cfg(not(feature = "std-thread"))]
             Message::ConnectToBonjourService(host, port, use_ssl) => {
-                let stream = self.connect_to_remote(&host, port, use_ssl)?;
-                self.write_stream = Some(stream);
+                self.connect_or_retry_later(&host, port, use_ssl);
                 self.process_log_queue()?;
             }
         }
         Ok(())
     }
 
-    pub async fn run_loop(&mut self) -> Result<(), Error> {
-        /*
-         * We are ready to run. Unpark the waiting threads now
-         */
-        self.ready_signal.signal();
+    fn drop_overflowing_messages(&mut self) {
+        let Some(max_queued_messages) = self.max_queued_messages else {
+            return;
+        };
+        while self.log_messages.len() > max_queued_messages {
+            /*
+             * Callers waiting for a dropped message are released, since it will never be
+             * written.
+             */
+            if let Some((message, signal)) = self.log_messages.pop_front() {
+                if DEBUG_LOGGER {
+                    log::warn!(
+                        "queue is full, dropping message {}",
+                        message.sequence_number
+                    );
+                }
+                if let Some(signal) = signal {
+                    signal.signal();
+                }
+                self.processed_count += 1;
+                self.stats.record_dropped();
+            }
+        }
+    }
 
-        while let Some(message) = self.message_rx.recv().await {
-            self.handle_message(message)?;
+    /// Flushes the stream and releases the callers once the messages they are waiting for have
+    /// been processed.
+    fn complete_flushes(&mut self) -> Result<(), Error> {
+        if !self
+            .pending_flushes
+            .iter()
+            .any(|(target, _)| *target <= self.processed_count)
+        {
+            return Ok(());
+        }
+        if let Some(stream) = self.write_stream.as_mut() {
+            stream.flush()?;
         }
+        let processed_count = self.processed_count;
+        self.pending_flushes.retain(|(target, signal)| {
+            if *target <= processed_count {
+                signal.signal();
+                return false;
+            }
+            true
+        });
+        Ok(())
+    }
 
-        self.close_buffer_write_stream()?;
+    fn set_connection_state(&mut self, state: ConnectionState) {
+        self.connection_state = state;
+        if state == ConnectionState::Connected {
+            self.was_connected = true;
+        }
+        self.status_tx.send_if_modified(|status| {
+            let modified = status.state != state;
+            status.state = state;
+            if state == ConnectionState::Disconnected {
+                status.host = None;
+                status.port = None;
+                status.tls = false;
+            }
+            modified
+        });
+    }
 
-        Ok(())
+    fn report_error(&self, err: &Error) {
+        self.status_tx.send_modify(|status| {
+            status.last_error = Some(err.to_string());
+        });
+        self.errors.report(err);
     }
 
     fn push_client_info_to_front_of_queue(&mut self) {
@@ -186,32 +505,48 @@ impl LogWorker {
         }
 
         self.log_messages
-            .push_front((LogMessage::client_info(), None));
-        self.connection_state = ConnectionState::Ready;
+            .push_front((LogMessage::client_info(&self.client_info), None));
+        self.set_connection_state(ConnectionState::Ready);
     }
 
     pub fn change_options(&mut self, mode: ConnectionMode) -> Result<(), Error> {
         if DEBUG_LOGGER {
             log::info!("changing options: {:?}. Closing/restarting.", mode);
         }
-        if self.write_stream.is_some() {
+        if self.connection_state != ConnectionState::Disconnected {
             self.disconnect();
         }
         self.connection_mode = mode;
+        self.was_connected = false;
+        self.reconnect_at = None;
+        self.reconnect_delay = MIN_RECONNECT_DELAY;
         self.setup_connection()?;
         Ok(())
     }
 
-    pub fn browse_bonjour_services(&mut self, use_ssl: bool) -> Result<(), Error> {
+    #[cfg(not(feature = "std-thread"))]
+    pub fn browse_bonjour_services(&mut self, service: BonjourServiceType) -> Result<(), Error> {
         self.command_tx
-            .send(network_manager::BonjourServiceType::Default(use_ssl))
+            .send(service)
             .map_err(|_| Error::ChannelNotAvailable)?;
 
-        self.connection_state = ConnectionState::Connecting;
+        self.set_connection_state(ConnectionState::Connecting);
 
         Ok(())
     }
 
+    #[cfg(feature = "std-thread")]
+    pub fn browse_bonjour_services(&mut self, _service: BonjourServiceType) -> Result<(), Error> {
+        /*
+         * NOTE the worker stays disconnected, and keeps the messages until another destination is
+         * selected.
+         */
+        self.report_error(&Error::InvalidConfiguration(
+            super::BONJOUR_UNSUPPORTED.to_string(),
+        ));
+        Ok(())
+    }
+
     pub fn connect_to_remote(
         &mut self,
         host: &str,
@@ -222,6 +557,7 @@ impl LogWorker {
         if DEBUG_LOGGER {
             log::info!("connecting to {connect_string}");
         }
+        self.set_connection_state(ConnectionState::Connecting);
         let stream = TcpStream::connect(connect_string)?;
         let stream = if use_ssl {
             if DEBUG_LOGGER {
@@ -229,15 +565,30 @@ impl LogWorker {
             }
 
             // FIXME Rework the whole connection sub-process.
-            let mut ssl_connector_builder = SslConnector::builder(SslMethod::tls()).unwrap();
-
-            ssl_connector_builder.set_verify(openssl::ssl::SslVerifyMode::NONE);
-            ssl_connector_builder
-                .set_verify_callback(openssl::ssl::SslVerifyMode::NONE, |_, _| true);
+            let mut ssl_connector_builder = SslConnector::builder(SslMethod::tls())
+                .map_err(|err| Error::Ssl(err.to_string()))?;
+
+            if self.tls.verify {
+                if let Some(ca_file) = &self.tls.ca_file {
+                    ssl_connector_builder
+                        .set_ca_file(ca_file)
+                        .map_err(|err| Error::Ssl(format!("{}: {err}", ca_file.display())))?;
+                }
+            } else {
+                ssl_connector_builder.set_verify(openssl::ssl::SslVerifyMode::NONE);
+                ssl_connector_builder
+                    .set_verify_callback(openssl::ssl::SslVerifyMode::NONE, |_, _| true);
+            }
 
+            /*
+             * NOTE without verification, the domain is only sent as SNI, which the desktop viewer
+             * ignores.
+             */
+            let domain = if self.tls.verify { host } else { "localhost" };
             let connector = ssl_connector_builder.build();
-            // if let WriteStreamWrapper::Tcp(inner_stream) = self.write_stream.take().unwrap() {
-            let stream = connector.connect("localhost", stream).unwrap();
+            let stream = connector
+                .connect(domain, stream)
+                .map_err(|err| Error::Ssl(err.to_string()))?;
             if DEBUG_LOGGER {
                 log::info!("opened SSL stream");
             }
@@ -246,22 +597,59 @@ impl LogWorker {
             WriteStreamWrapper::Tcp(stream)
         };
 
-        self.connection_state = ConnectionState::Connected;
+        self.status_tx.send_modify(|status| {
+            status.host = Some(host.to_string());
+            status.port = Some(port);
+            status.tls = use_ssl;
+        });
+        self.set_connection_state(ConnectionState::Connected);
 
         Ok(stream)
     }
 
+    /// Connects to the desktop viewer. When it can't be reached, the error is reported and the
+    /// worker stays disconnected until the next attempt, with an exponential backoff, keeping the
+    /// queued messages.
+    fn connect_or_retry_later(&mut self, host: &str, port: u16, use_ssl: bool) {
+        match self.connect_to_remote(host, port, use_ssl) {
+            Ok(stream) => {
+                self.write_stream = Some(stream);
+                self.reconnect_at = None;
+                self.reconnect_delay = MIN_RECONNECT_DELAY;
+            }
+            Err(err) => {
+                if DEBUG_LOGGER {
+                    log::warn!("couldn't connect to {host}:{port}: {err}");
+                }
+                self.report_error(&err);
+                self.set_connection_state(ConnectionState::Disconnected);
+                self.reconnect_at = Some(Instant::now() + self.reconnect_delay);
+                self.reconnect_delay = (self.reconnect_delay * 2).min(MAX_RECONNECT_DELAY);
+            }
+        }
+    }
+
+    /// Retries to connect once the backoff delay expired, and writes the queued messages.
+    fn retry_connection(&mut self) -> Result<(), Error> {
+        self.reconnect_at = None;
+        self.process_log_queue()?;
+        self.complete_flushes()
+    }
+
     pub fn disconnect(&mut self) {
         if DEBUG_LOGGER {
             log::info!("disconnect_from_remote()");
         }
 
-        self.connection_state = ConnectionState::Disconnected;
+        self.set_connection_state(ConnectionState::Disconnected);
         self.write_stream = None;
         self.sequence_generator = 1;
     }
 
     pub fn setup_connection(&mut self) -> Result<(), Error> {
+        if self.was_connected && self.connection_state == ConnectionState::Disconnected {
+            self.stats.record_reconnect_attempt();
+        }
         match self.connection_mode.clone() {
             ConnectionMode::File(path) => {
                 let stream = self.create_buffer_write_stream(&path)?;
@@ -270,13 +658,12 @@ impl LogWorker {
             ConnectionMode::Tcp(host, port, use_ssl)
                 if self.connection_state == ConnectionState::Disconnected =>
             {
-                let stream = self.connect_to_remote(&host, port, use_ssl)?;
-                self.write_stream = Some(stream);
+                self.connect_or_retry_later(&host, port, use_ssl);
             }
-            ConnectionMode::Bonjour(BonjourServiceType::Default(use_ssl))
+            ConnectionMode::Bonjour(service)
                 if self.connection_state == ConnectionState::Disconnected =>
             {
-                self.browse_bonjour_services(use_ssl)?;
+                self.browse_bonjour_services(service)?;
             }
             _ => {
                 // Nothing to do
@@ -304,7 +691,8 @@ impl LogWorker {
         }
 
         let file_writer = BufWriter::new(File::create(path)?);
-        self.connection_state = ConnectionState::Connected;
+        self.file_size = 0;
+        self.set_connection_state(ConnectionState::Connected);
         Ok(WriteStreamWrapper::File(file_writer))
     }
 
@@ -316,7 +704,51 @@ impl LogWorker {
         if let Some(mut stream) = self.write_stream.take() {
             stream.flush()?;
         };
-        self.connection_state = ConnectionState::Disconnected;
+        self.set_connection_state(ConnectionState::Disconnected);
+        Ok(())
+    }
+
+    /// Whether the current log file must be rotated before writing a message of the given length.
+    fn needs_rotation(&self, length: u64) -> bool {
+        matches!(self.connection_mode, ConnectionMode::File(_))
+            && self.rotation.is_some_and(|rotation| {
+                self.file_size > 0 && self.file_size + length > rotation.max_size
+            })
+    }
+
+    /// Closes the current log file, shifts the rotated ones (`output.log` becoming
+    /// `output.log.1`, `output.log.1` becoming `output.log.2`, and so on, the oldest being
+    /// overwritten), and starts a new file at the same path.
+    fn rotate_log_file(&mut self) -> Result<(), Error> {
+        let (ConnectionMode::File(path), Some(rotation)) =
+            (self.connection_mode.clone(), self.rotation)
+        else {
+            return Ok(());
+        };
+        if DEBUG_LOGGER {
+            log::info!("rotating log file {path:?}");
+        }
+        if let Some(mut stream) = self.write_stream.take() {
+            stream.flush()?;
+        }
+        let rotated_path = |index: usize| {
+            let mut rotated_path = path.clone().into_os_string();
+            rotated_path.push(format!(".{index}"));
+            PathBuf::from(rotated_path)
+        };
+        for index in (1..rotation.max_files).rev() {
+            let older_path = rotated_path(index);
+            if older_path.exists() {
+                fs::rename(&older_path, rotated_path(index + 1))?;
+            }
+        }
+        if rotation.max_files > 0 {
+            fs::rename(&path, rotated_path(1))?;
+        }
+        self.write_stream = Some(WriteStreamWrapper::File(BufWriter::new(File::create(
+            &path,
+        )?)));
+        self.file_size = 0;
         Ok(())
     }
 
@@ -337,8 +769,18 @@ impl LogWorker {
 
                 message.freeze();
                 let length = message.data.len();
+                if self.needs_rotation(length as u64) {
+                    self.log_messages.push_front((message, signal));
+                    self.rotate_log_file()?;
+                    self.push_client_info_to_front_of_queue();
+                    continue;
+                }
 
-                let tcp_stream = self.write_stream.as_mut().unwrap();
+                let Some(tcp_stream) = self.write_stream.as_mut() else {
+                    self.log_messages.push_front((message, signal));
+                    self.disconnect();
+                    return Ok(());
+                };
                 if DEBUG_LOGGER {
                     log::info!("writing to {:?} (len: {length})", tcp_stream);
                 }
@@ -347,11 +789,18 @@ impl LogWorker {
                     if DEBUG_LOGGER {
                         log::warn!("write to stream failed: {err:?}");
                     }
+                    self.report_error(&Error::IO(err));
 
                     self.disconnect();
                     self.try_reconnecting()?;
                     return Ok(());
                 }
+                self.file_size += length as u64;
+                let is_log_message = message.message_type != LogMessageType::ClientInfo;
+                self.stats.record_written(length, is_log_message);
+                if is_log_message {
+                    self.processed_count += 1;
+                }
                 if let Some(signal) = signal {
                     tcp_stream.flush()?;
                     signal.signal();
@@ -359,7 +808,7 @@ impl LogWorker {
             }
         }
 
-        Ok(())
+        self.complete_flushes()
     }
 
     pub fn process_log_queue(&mut self) -> Result<(), Error> {
@@ -374,7 +823,7 @@ impl LogWorker {
             log::info!("process_log_queue");
         }
 
-        if self.connection_state == ConnectionState::Disconnected {
+        if self.connection_state == ConnectionState::Disconnected && self.reconnect_at.is_none() {
             self.setup_connection()?;
         }
         if self.connection_state == ConnectionState::Connected {
@@ -397,7 +846,7 @@ impl Drop for LogWorker {
             log::info!("calling drop for log worker");
         }
         if let Some(mut stream) = self.write_stream.take() {
-            stream.flush().unwrap();
+            let _ = stream.flush();
         }
         self.disconnect();
     }
@@ -0,0 +1,374 @@
+use std::{
+    path::PathBuf,
+    sync::{atomic::AtomicBool, Arc, RwLock},
+};
+
+#[cfg(not(feature = "std-thread"))]
+use tokio::runtime::Handle;
+
+use crate::nslogger::{
+    log_message::ClientInfo,
+    log_worker::{FileRotation, TlsOptions, WorkerConfig},
+    BonjourServiceType, ConnectionMode, Domain, DomainMapping, Error, ErrorHandler, KeyValueText,
+    Logger, LoggerConfig, Message, TargetFilter, BONJOUR_UNSUPPORTED,
+};
+
+/// Collects the configuration of a [`Logger`], which is validated as a whole when calling
+/// [`LoggerBuilder::build`].
+///
+/// Unlike the `Logger` setters, the connection to the desktop viewer is set up only once.
+///
+/// ```rust,no_run
+/// use nslogger::{Domain, LoggerBuilder};
+///
+/// let log = LoggerBuilder::new()
+///     .filter(log::LevelFilter::Info)
+///     .remote_host("127.0.0.1", 50000)
+///     .use_ssl(false)
+///     .max_queued_messages(1000)
+///     .client_name("my_app")
+///     .target_domain("my_app::db", Domain::DB)
+///     .build()
+///     .expect("a valid configuration");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct LoggerBuilder {
+    filter: TargetFilter,
+    connection_mode: ConnectionMode,
+    use_ssl: Option<bool>,
+    tls_verify: Option<bool>,
+    tls_ca_file: Option<PathBuf>,
+    flush_messages: bool,
+    rotation: Option<FileRotation>,
+    max_queued_messages: Option<usize>,
+    client_info: ClientInfo,
+    domain_mapping: DomainMapping,
+    key_value_parts: Option<bool>,
+    key_value_text: KeyValueText,
+    error_handler: Option<ErrorHandler>,
+    #[cfg(not(feature = "std-thread"))]
+    runtime: Option<Handle>,
+}
+
+impl LoggerBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builder with the given options, e.g. part of the settings of the application. Fails if the
+    /// destination is invalid, e.g. a `host` without port.
+    pub fn from_config(config: LoggerConfig) -> Result<Self, Error> {
+        config.builder()
+    }
+
+    /// Maximum level of the messages sent through the `log` facade, possibly by target. Defaults
+    /// to `Warn`.
+    pub fn filter(mut self, filter: impl Into<TargetFilter>) -> Self {
+        self.filter = filter.into();
+        self
+    }
+
+    /// Destination of the messages. Defaults to the SSL Bonjour service, which must be replaced
+    /// with the `std-thread` feature.
+    pub fn connection_mode(mut self, mode: ConnectionMode) -> Self {
+        self.connection_mode = mode;
+        self
+    }
+
+    /// Looks up the desktop viewer through the given Bonjour service.
+    pub fn bonjour_service(self, service: BonjourServiceType) -> Self {
+        self.connection_mode(ConnectionMode::Bonjour(service))
+    }
+
+    /// Connects directly to the desktop viewer. SSL is used unless disabled with
+    /// [`LoggerBuilder::use_ssl`].
+    pub fn remote_host(self, host_name: &str, host_port: u16) -> Self {
+        self.connection_mode(ConnectionMode::Tcp(host_name.to_string(), host_port, true))
+    }
+
+    /// Writes the messages to the given file.
+    pub fn log_file_path(self, file_path: impl Into<PathBuf>) -> Self {
+        self.connection_mode(ConnectionMode::File(file_path.into()))
+    }
+
+    /// Rotates the log file once it would grow past `max_size` bytes: the file is renamed with a
+    /// `.1` suffix, the previous ones being shifted to `.2` and so on, keeping at most `max_files`
+    /// of them, and a new file is started. Only applicable to file logging.
+    pub fn rotate_log_file(mut self, max_size: u64, max_files: usize) -> Self {
+        self.rotation = Some(FileRotation {
+            max_size,
+            max_files,
+        });
+        self
+    }
+
+    /// Overrides the SSL setting of the connection mode. Not applicable to file logging.
+    pub fn use_ssl(mut self, use_ssl: bool) -> Self {
+        self.use_ssl = Some(use_ssl);
+        self
+    }
+
+    /// Defines whether the certificate of the desktop viewer is verified, along with its host
+    /// name, when connecting with SSL. Disabled by default, as the desktop viewer uses a
+    /// self-signed certificate unless configured otherwise.
+    pub fn tls_verify(mut self, verify: bool) -> Self {
+        self.tls_verify = Some(verify);
+        self
+    }
+
+    /// Trusts the certificates of the given PEM file, in addition to the system ones, when
+    /// verifying the certificate of the desktop viewer. Enables the verification unless disabled
+    /// with [`LoggerBuilder::tls_verify`].
+    pub fn tls_ca_file(mut self, ca_file: impl Into<PathBuf>) -> Self {
+        self.tls_ca_file = Some(ca_file.into());
+        self
+    }
+
+    /// Defines whether each log call waits for its message to be written before returning.
+    pub fn flush_messages(mut self, flush_messages: bool) -> Self {
+        self.flush_messages = flush_messages;
+        self
+    }
+
+    /// Maximum number of messages kept while waiting for the desktop viewer. The oldest messages
+    /// are dropped first. Unbounded by default.
+    pub fn max_queued_messages(mut self, max_queued_messages: usize) -> Self {
+        self.max_queued_messages = Some(max_queued_messages);
+        self
+    }
+
+    /// Client name displayed by the desktop viewer. Defaults to the executable name.
+    pub fn client_name(mut self, name: &str) -> Self {
+        self.client_info.name = Some(name.to_string());
+        self
+    }
+
+    /// Client version displayed by the desktop viewer.
+    pub fn client_version(mut self, version: &str) -> Self {
+        self.client_info.version = Some(version.to_string());
+        self
+    }
+
+    /// Maps the records whose target starts with `target_prefix` to the given domain, instead of
+    /// a custom domain named after the target. The longest matching prefix wins.
+    pub fn target_domain(mut self, target_prefix: &str, domain: Domain) -> Self {
+        self.domain_mapping = self.domain_mapping.rule(target_prefix, domain);
+        self
+    }
+
+    /// Replaces the mapping from the targets of the records to the domains, including the rules
+    /// added with [`LoggerBuilder::target_domain`].
+    pub fn domain_mapping(mut self, mapping: DomainMapping) -> Self {
+        self.domain_mapping = mapping;
+        self
+    }
+
+    /// Defines whether the key-value pairs of the records are sent as separate message parts,
+    /// keeping the integers typed. Enabled by default.
+    pub fn key_value_parts(mut self, key_value_parts: bool) -> Self {
+        self.key_value_parts = Some(key_value_parts);
+        self
+    }
+
+    /// Style in which the key-value pairs of the records are appended to the message text. Not
+    /// appended by default.
+    pub fn key_value_text(mut self, style: KeyValueText) -> Self {
+        self.key_value_text = style;
+        self
+    }
+
+    /// Callback receiving the errors that occur while logging. See [`Logger::set_error_handler`].
+    pub fn on_error(mut self, handler: impl Fn(&Error) + Send + Sync + 'static) -> Self {
+        self.error_handler = Some(ErrorHandler::new(handler));
+        self
+    }
+
+    /// Runs the worker of the logger on the given runtime. See [`Logger::with_runtime_handle`].
+    #[cfg(not(feature = "std-thread"))]
+    pub fn runtime_handle(mut self, runtime: Handle) -> Self {
+        self.runtime = Some(runtime);
+        self
+    }
+
+    fn validate(&self) -> Result<ConnectionMode, Error> {
+        let invalid = |reason: &str| Err(Error::InvalidConfiguration(reason.to_string()));
+        let connection_mode = match (self.connection_mode.clone(), self.use_ssl) {
+            (ConnectionMode::File(path), _) if path.as_os_str().is_empty() => {
+                return invalid("log file path is empty");
+            }
+            (ConnectionMode::File(_), Some(_)) => {
+                return invalid("SSL can't be configured when logging to a file");
+            }
+            (ConnectionMode::Tcp(host, ..), _) if host.is_empty() => {
+                return invalid("remote host name is empty");
+            }
+            (ConnectionMode::Tcp(_, 0, _), _) => return invalid("remote host port is 0"),
+            (ConnectionMode::Tcp(host, port, use_ssl), ssl_override) => {
+                ConnectionMode::Tcp(host, port, ssl_override.unwrap_or(use_ssl))
+            }
+            (ConnectionMode::Bonjour(BonjourServiceType::Custom(service, _)), _)
+                if service.is_empty() =>
+            {
+                return invalid("Bonjour service type is empty");
+            }
+            (
+                ConnectionMode::Bonjour(BonjourServiceType::Custom(service, use_ssl)),
+                ssl_override,
+            ) => ConnectionMode::Bonjour(BonjourServiceType::Custom(
+                service,
+                ssl_override.unwrap_or(use_ssl),
+            )),
+            (ConnectionMode::Bonjour(BonjourServiceType::Default(use_ssl)), ssl_override) => {
+                ConnectionMode::Bonjour(BonjourServiceType::Default(
+                    ssl_override.unwrap_or(use_ssl),
+                ))
+            }
+            (mode, _) => mode,
+        };
+        if cfg!(feature = "std-thread") && matches!(connection_mode, ConnectionMode::Bonjour(_)) {
+            return invalid(BONJOUR_UNSUPPORTED);
+        }
+        if matches!(connection_mode, ConnectionMode::File(_))
+            && (self.tls_verify.is_some() || self.tls_ca_file.is_some())
+        {
+            return invalid("TLS can't be configured when logging to a file");
+        }
+        if self.tls_verify == Some(false) && self.tls_ca_file.is_some() {
+            return invalid("TLS CA file given without certificate verification");
+        }
+        match self.rotation {
+            Some(_) if !matches!(connection_mode, ConnectionMode::File(_)) => {
+                return invalid("log file rotation requires logging to a file");
+            }
+            Some(FileRotation { max_size: 0, .. }) => {
+                return invalid("log files can't be limited to 0 bytes");
+            }
+            _ => {}
+        }
+        if self.max_queued_messages == Some(0) {
+            return invalid("message queue can't be limited to 0 messages");
+        }
+        self.domain_mapping.validate()?;
+        Ok(connection_mode)
+    }
+
+    /// Validates the configuration and creates the logger.
+    pub fn build(self) -> Result<Logger, Error> {
+        let connection_mode = self.validate()?;
+        #[cfg(not(feature = "std-thread"))]
+        let mut logger = match &self.runtime {
+            Some(runtime) => Logger::with_runtime_handle(runtime),
+            None => Logger::spawn()?,
+        };
+        #[cfg(feature = "std-thread")]
+        let mut logger = Logger::spawn()?;
+        let core = Arc::get_mut(&mut logger.core).expect("new logger isn't shared");
+        core.filter = RwLock::new(self.filter);
+        core.flush_messages = AtomicBool::new(self.flush_messages);
+        core.domain_mapping = self.domain_mapping;
+        core.key_value_parts = self.key_value_parts.unwrap_or(true);
+        core.key_value_text = self.key_value_text;
+        logger.set_error_handler_from(self.error_handler);
+        logger
+            .core
+            .worker
+            .message_tx
+            .send(Message::Configure(WorkerConfig {
+                connection_mode,
+                max_queued_messages: self.max_queued_messages,
+                client_info: self.client_info,
+                tls: TlsOptions {
+                    verify: self.tls_verify.unwrap_or(self.tls_ca_file.is_some()),
+                    ca_file: self.tls_ca_file,
+                },
+                rotation: self.rotation,
+            }))
+            .map_err(|_| Error::ChannelNotAvailable)?;
+        Ok(logger)
+    }
+}
+
+impl TryFrom<LoggerConfig> for LoggerBuilder {
+    type Error = Error;
+
+    fn try_from(config: LoggerConfig) -> Result<Self, Error> {
+        Self::from_config(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overrides_ssl_setting() {
+        let builder = LoggerBuilder::new()
+            .remote_host("127.0.0.1", 50000)
+            .use_ssl(false);
+        assert_eq!(
+            ConnectionMode::Tcp("127.0.0.1".to_string(), 50000, false),
+            builder.validate().unwrap()
+        );
+    }
+
+    #[test]
+    fn rejects_inconsistent_configuration() {
+        let builder = LoggerBuilder::new()
+            .log_file_path("/tmp/file_output.log")
+            .use_ssl(true);
+        assert!(matches!(
+            builder.validate(),
+            Err(Error::InvalidConfiguration(_))
+        ));
+        let builder = LoggerBuilder::new().remote_host("127.0.0.1", 0);
+        assert!(matches!(
+            builder.validate(),
+            Err(Error::InvalidConfiguration(_))
+        ));
+        let builder = LoggerBuilder::new().max_queued_messages(0);
+        assert!(matches!(
+            builder.validate(),
+            Err(Error::InvalidConfiguration(_))
+        ));
+        let builder = LoggerBuilder::new()
+            .remote_host("127.0.0.1", 50000)
+            .rotate_log_file(1 << 20, 3);
+        assert!(matches!(
+            builder.validate(),
+            Err(Error::InvalidConfiguration(_))
+        ));
+        let builder = LoggerBuilder::new()
+            .log_file_path("/tmp/file_output.log")
+            .rotate_log_file(0, 3);
+        assert!(matches!(
+            builder.validate(),
+            Err(Error::InvalidConfiguration(_))
+        ));
+        let builder = LoggerBuilder::new()
+            .log_file_path("/tmp/file_output.log")
+            .tls_verify(true);
+        assert!(matches!(
+            builder.validate(),
+            Err(Error::InvalidConfiguration(_))
+        ));
+        let builder = LoggerBuilder::new()
+            .remote_host("127.0.0.1", 50000)
+            .tls_ca_file("/tmp/viewer.pem")
+            .tls_verify(false);
+        assert!(matches!(
+            builder.validate(),
+            Err(Error::InvalidConfiguration(_))
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "std-thread")]
+    fn rejects_bonjour_without_runtime() {
+        let builder = LoggerBuilder::new().bonjour_service(BonjourServiceType::Default(false));
+        assert!(matches!(
+            builder.validate(),
+            Err(Error::InvalidConfiguration(_))
+        ));
+        assert!(matches!(Logger::new(), Err(Error::InvalidConfiguration(_))));
+    }
+}
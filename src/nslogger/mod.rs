@@ -1,80 +1,400 @@
 use std::{
+    fmt,
+    future::Future,
     path::{Path, PathBuf},
+    pin::Pin,
     str::FromStr,
-    sync::{Arc, Condvar, LazyLock, Mutex},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Condvar, Mutex, OnceLock, RwLock,
+    },
+    task::{Context, Poll, Waker},
+    time::{Duration, Instant},
 };
 
 use cfg_if::cfg_if;
-use chrono;
-use tokio::sync::mpsc;
+#[cfg(not(feature = "std-thread"))]
+use tokio::runtime::Handle;
+use tokio::sync::watch;
+
+#[cfg(not(any(feature = "tokio-runtime", feature = "std-thread")))]
+compile_error!("either the `tokio-runtime` or the `std-thread` feature must be enabled");
+#[cfg(all(feature = "signals", feature = "std-thread"))]
+compile_error!("the `signals` feature requires the tokio runtime, which `std-thread` replaces");
+#[cfg(all(
+    feature = "tokio-runtime",
+    feature = "std-thread",
+    not(feature = "signals")
+))]
+compile_error!(
+    "the `tokio-runtime` and `std-thread` features are mutually exclusive: disable the default \
+     features to use `std-thread`"
+);
 
 const DEBUG_LOGGER: bool = true & cfg!(test);
 
-#[cfg(test)]
-use std::sync::Once;
+/// Maximum time spent by [`log::Log::flush`] waiting for the queued messages to be written.
+pub(crate) const DEFAULT_FLUSH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Maximum time spent draining the queued messages when the process exits.
+pub(crate) const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+const BONJOUR_UNSUPPORTED: &str = "Bonjour isn't supported in the std-thread mode";
 
 #[cfg(test)]
-use env_logger;
+use std::sync::Once;
 
 #[cfg(test)]
 static START: Once = Once::new();
 
-static RUNTIME: LazyLock<ReferenceCountedRuntime> =
-    LazyLock::new(|| ReferenceCountedRuntime::new().unwrap());
+#[cfg(not(feature = "std-thread"))]
+static RUNTIME: OnceLock<Result<ReferenceCountedRuntime, String>> = OnceLock::new();
+
+/// Message channels of the workers spawned so far, to drain them on shutdown.
+static WORKERS: Mutex<Vec<channel::WeakUnboundedSender<Message>>> = Mutex::new(Vec::new());
+
+#[cfg(not(feature = "std-thread"))]
+fn runtime() -> Result<&'static ReferenceCountedRuntime, Error> {
+    RUNTIME
+        .get_or_init(|| ReferenceCountedRuntime::new().map_err(|err| err.to_string()))
+        .as_ref()
+        .map_err(|err| Error::RuntimeUnavailable(err.clone()))
+}
+
+/// Logger installed by [`set_global_logger`], whose filter is synced with the `log` facade.
+static GLOBAL_LOGGER: OnceLock<&'static Logger> = OnceLock::new();
+
+/// Installs the logger as the global `log` facade logger, for the rest of the process.
+pub(crate) fn set_global_logger(logger: Logger) -> Result<&'static Logger, Error> {
+    let max_level = logger.core.filter.read().unwrap().max_level();
+    let logger: &'static Logger = Box::leak(Box::new(logger));
+    log::set_logger(logger)?;
+    log::set_max_level(max_level);
+    let _ = GLOBAL_LOGGER.set(logger);
+    Ok(logger)
+}
+
+/// Returns the global logger, if installed by [`set_global_logger`].
+pub(crate) fn global_logger() -> Option<&'static Logger> {
+    GLOBAL_LOGGER.get().copied()
+}
+
+/// Keeps track of a new worker, to drain it on shutdown.
+fn register_worker(worker: WorkerHandle) -> WorkerHandle {
+    let mut workers = WORKERS.lock().unwrap();
+    workers.retain(|message_tx| message_tx.strong_count() > 0);
+    workers.push(worker.message_tx.downgrade());
+    worker
+}
+
+/// Waits for the messages queued by all the loggers to be written, then closes their connections
+/// and stops their workers. Returns `false` if that didn't happen before the timeout expired.
+pub(crate) fn shutdown(timeout: Duration) -> bool {
+    let deadline = Instant::now() + timeout;
+    let signals: Vec<_> = WORKERS
+        .lock()
+        .unwrap()
+        .drain(..)
+        .filter_map(|message_tx| message_tx.upgrade())
+        .filter_map(|message_tx| {
+            let signal = Signal::default();
+            message_tx
+                .send(Message::Shutdown(signal.clone()))
+                .is_ok()
+                .then_some(signal)
+        })
+        .collect();
+    signals
+        .iter()
+        .all(|signal| signal.wait_timeout(deadline.saturating_duration_since(Instant::now())))
+}
 
+/// Drains the queued messages and exits the process on SIGINT or SIGTERM.
+#[cfg(all(feature = "signals", not(feature = "std-thread")))]
+pub(crate) fn install_signal_handlers() -> Result<(), Error> {
+    let handle = runtime()?.handle();
+    let _guard = handle.enter();
+    cfg_if! {
+        if #[cfg(unix)] {
+            use tokio::signal::unix::{signal, SignalKind};
+            let mut interrupt = signal(SignalKind::interrupt())?;
+            let mut terminate = signal(SignalKind::terminate())?;
+            let termination = async move {
+                tokio::select! {
+                    _ = interrupt.recv() => 130,
+                    _ = terminate.recv() => 143,
+                }
+            };
+        } else {
+            let termination = async {
+                let _ = tokio::signal::ctrl_c().await;
+                130
+            };
+        }
+    }
+    handle.spawn(async move {
+        let exit_code = termination.await;
+        if DEBUG_LOGGER {
+            log::info!("termination signal received, shutting down");
+        }
+        let _ = tokio::task::spawn_blocking(move || {
+            shutdown(DEFAULT_SHUTDOWN_TIMEOUT);
+            std::process::exit(exit_code)
+        })
+        .await;
+    });
+    Ok(())
+}
+
+mod channel;
+mod config;
+mod context;
+mod domain_mapping;
+mod filter;
+mod key_values;
 mod log_message;
 mod log_worker;
+mod logger_builder;
+#[cfg(not(feature = "std-thread"))]
 mod network_manager;
+#[cfg(not(feature = "std-thread"))]
 mod reference_counted_runtime;
+#[cfg(feature = "slog")]
+mod slog_drain;
+mod stats;
+mod supervisor;
+#[cfg(feature = "tracing")]
+mod tracing_layer;
+mod worker_handle;
 
 #[cfg(test)]
 pub(crate) use self::log_message::{MessagePartType, SEQUENCE_NB_OFFSET};
+#[cfg(not(feature = "std-thread"))]
+pub(crate) use self::reference_counted_runtime::ReferenceCountedRuntime;
+#[cfg(feature = "slog")]
+pub use self::slog_drain::NSLoggerDrain;
+#[cfg(feature = "tracing")]
+pub use self::tracing_layer::NSLoggerLayer;
+pub use self::{
+    config::LoggerConfig,
+    context::{with_context, with_context_async, WithContext},
+    domain_mapping::DomainMapping,
+    filter::TargetFilter,
+    key_values::KeyValueText,
+    log_message::Domain,
+    log_worker::{BonjourServiceType, ConnectionMode, ConnectionState, ConnectionStatus},
+    logger_builder::LoggerBuilder,
+    stats::LoggerStats,
+    supervisor::{WorkerState, WorkerStatus},
+};
 pub(crate) use self::{
+    key_values::KeyValues,
     log_message::{LogMessage, LogMessageType, MessagePartKey},
-    log_worker::{ConnectionMode, LogWorker, Message},
-    network_manager::BonjourServiceType,
-    reference_counted_runtime::ReferenceCountedRuntime,
+    log_worker::{LogWorker, Message},
+    worker_handle::WorkerHandle,
 };
-pub use crate::nslogger::log_message::Domain;
+
+#[derive(Debug, Default)]
+struct SignalState {
+    ready: bool,
+    /// Tasks waiting asynchronously for the signal.
+    wakers: Vec<Waker>,
+}
 
 #[derive(Debug, Clone, Default)]
-pub struct Signal(Arc<(Mutex<bool>, Condvar)>);
+pub struct Signal(Arc<(Mutex<SignalState>, Condvar)>);
 
 impl Signal {
     pub fn wait(&self) {
-        let mut ready = self.0 .0.lock().unwrap();
-        while !*ready {
-            ready = self.0 .1.wait(ready).unwrap();
+        let (lock, condvar) = &*self.0;
+        let mut state = lock.lock().unwrap();
+        while !state.ready {
+            state = condvar.wait(state).unwrap();
         }
     }
 
+    /// Waits until signaled, or until the timeout expires. Returns whether the signal was
+    /// received.
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        let (lock, condvar) = &*self.0;
+        let state = lock.lock().unwrap();
+        let (state, _) = condvar
+            .wait_timeout_while(state, timeout, |state| !state.ready)
+            .unwrap();
+        state.ready
+    }
+
+    /// Returns a future resolved once signaled, which doesn't block the calling thread.
+    pub fn wait_async(&self) -> SignalFuture {
+        SignalFuture(self.clone())
+    }
+
     pub fn signal(&self) {
-        let mut ready = self.0 .0.lock().unwrap();
-        *ready = true;
-        self.0 .1.notify_all();
+        let (lock, condvar) = &*self.0;
+        let mut state = lock.lock().unwrap();
+        state.ready = true;
+        condvar.notify_all();
+        state.wakers.drain(..).for_each(Waker::wake);
     }
 }
 
+/// Future returned by [`Signal::wait_async`].
+#[derive(Debug)]
+pub struct SignalFuture(Signal);
+
+impl Future for SignalFuture {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let Signal(inner) = &self.0;
+        let mut state = inner.0.lock().unwrap();
+        if state.ready {
+            return Poll::Ready(());
+        }
+        if !state.wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+            state.wakers.push(cx.waker().clone());
+        }
+        Poll::Pending
+    }
+}
+
+/// Errors reported by the logger configuration API.
 #[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
 pub enum Error {
+    /// The logging worker is not running anymore, or its message channel was closed.
     #[error("channel was closed or end was dropped")]
     ChannelNotAvailable,
-    #[error("IO error")]
+    /// An I/O error occurred while setting up the logger.
+    #[error("IO error: {_0}")]
     IO(#[from] std::io::Error),
+    /// The given log file path could not be parsed.
     #[error("invalid file path: {_0}")]
     InvalidPath(String),
+    /// The logger configuration is inconsistent.
+    #[error("invalid configuration: {_0}")]
+    InvalidConfiguration(String),
+    /// The runtime running the logging worker couldn't be started.
+    #[error("logger runtime unavailable: {_0}")]
+    RuntimeUnavailable(String),
+    /// The SSL connection to the desktop viewer couldn't be established.
+    #[error("SSL error: {_0}")]
+    Ssl(String),
+    /// Another global logger was already installed.
+    #[error("global logger already set")]
+    SetLogger(#[from] log::SetLoggerError),
 }
 
+/// Callback receiving the errors that occur while logging.
+#[derive(Clone)]
+pub(crate) struct ErrorHandler(Arc<dyn Fn(&Error) + Send + Sync>);
+
+impl fmt::Debug for ErrorHandler {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("ErrorHandler")
+    }
+}
+
+impl ErrorHandler {
+    pub fn new(handler: impl Fn(&Error) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(handler))
+    }
+}
+
+/// Counts the errors that occur while logging, and forwards them to the error handler, if any.
+///
+/// Shared by the loggers and their worker, so that the errors of both sides are reported.
+#[derive(Debug, Default)]
+pub(crate) struct ErrorReporter {
+    count: AtomicU64,
+    handler: RwLock<Option<ErrorHandler>>,
+}
+
+pub(crate) type SharedErrors = Arc<ErrorReporter>;
+
+impl ErrorReporter {
+    pub(crate) fn report(&self, error: &Error) {
+        if DEBUG_LOGGER {
+            log::warn!("logging error: {error}");
+        }
+        self.count.fetch_add(1, Ordering::Relaxed);
+        if let Some(handler) = self.handler.read().unwrap().as_ref() {
+            (handler.0)(error);
+        }
+    }
+}
+
+/// Sends messages to the desktop viewer, or to a file, through a background worker of its own.
+///
+/// Each logger has its own connection, sequence numbers and client info, so that e.g. a subsystem
+/// can log to a file while the rest of the application logs to the desktop viewer.
+///
+/// [`Logger::with_domain`], [`Logger::with_level`] and [`Logger::with_fields`] return child
+/// handles sharing the worker and the configuration of the logger. Only the domain, level and
+/// fields are specific to a handle: the setters, e.g. [`Logger::set_filter`], change the whole
+/// logger, i.e. the parent handle and all its children.
+#[derive(Clone)]
 pub struct Logger {
-    ready_signal: Signal,
-    message_tx: mpsc::UnboundedSender<Message>,
-    filter: log::LevelFilter,
+    core: Arc<LoggerCore>,
+    /// Domain of the messages logged without domain.
+    domain: Option<Domain>,
+    /// Level of the messages logged without level, e.g. through [`Logger::log`].
+    level: Option<log::Level>,
+    /// Key-value pairs added to every message.
+    fields: KeyValues,
+}
+
+/// Worker and configuration shared by a logger and its child handles.
+struct LoggerCore {
+    worker: WorkerHandle,
+    filter: RwLock<TargetFilter>,
     /// Wait for each message to be sent to the desktop viewer (includes connecting to the viewer)
-    flush_messages: bool,
+    flush_messages: AtomicBool,
+    /// Domains of the records sent through the `log` facade.
+    domain_mapping: DomainMapping,
+    /// Send the key-value pairs of the records as separate message parts.
+    key_value_parts: bool,
+    /// Append the key-value pairs of the records to the message text.
+    key_value_text: KeyValueText,
 }
 
 impl Logger {
+    /// Creates a logger that looks up the desktop viewer through Bonjour, with a `Warn` level
+    /// filter. The logger gets its own worker and connection, run by a runtime shared by all the
+    /// loggers.
+    ///
+    /// Fails with the `std-thread` feature, which doesn't support Bonjour, like
+    /// [`LoggerBuilder::build`]: the destination must be given to the builder instead.
     pub fn new() -> Result<Self, Error> {
+        if cfg!(feature = "std-thread") {
+            return Err(Error::InvalidConfiguration(BONJOUR_UNSUPPORTED.to_string()));
+        }
+        Self::spawn()
+    }
+
+    /// Creates a logger with its own worker, to be configured by [`LoggerBuilder::build`].
+    pub(crate) fn spawn() -> Result<Self, Error> {
+        cfg_if! {
+            if #[cfg(feature = "std-thread")] {
+                let worker = WorkerHandle::spawn()?;
+            } else {
+                let worker = WorkerHandle::spawn(&runtime()?.handle());
+            }
+        }
+        Ok(Self::from_worker(register_worker(worker)))
+    }
+
+    /// Same as [`Logger::new`], but runs the worker on the given runtime instead of a dedicated
+    /// one. The runtime must be multi-threaded, with the IO and time drivers enabled, since the
+    /// log calls may block until the worker is ready.
+    ///
+    /// See [`Logger::shutdown_async`] to wait for the worker to stop.
+    #[cfg(not(feature = "std-thread"))]
+    pub fn with_runtime_handle(runtime: &Handle) -> Self {
+        Self::from_worker(register_worker(WorkerHandle::spawn(runtime)))
+    }
+
+    fn from_worker(worker: WorkerHandle) -> Self {
         if DEBUG_LOGGER {
             cfg_if! {
                 if #[cfg(test)] {
@@ -92,39 +412,96 @@ impl Logger {
 
             init_test_logger();
         }
-        let (ready_signal, message_tx) = (*RUNTIME).get_signal_and_sender();
+        Logger {
+            core: Arc::new(LoggerCore {
+                worker,
+                filter: RwLock::default(),
+                flush_messages: AtomicBool::new(false),
+                domain_mapping: DomainMapping::default(),
+                key_value_parts: true,
+                key_value_text: KeyValueText::default(),
+            }),
+            domain: None,
+            level: None,
+            fields: KeyValues::default(),
+        }
+    }
 
-        Ok(Logger {
-            message_tx,
-            ready_signal,
-            filter: log::LevelFilter::Warn,
-            flush_messages: false,
-        })
+    /// Returns a handle logging through the same worker, with the given domain for the messages
+    /// logged without domain, including the records sent through the `log` facade.
+    ///
+    /// ```rust,no_run
+    /// use nslogger::{Domain, Logger};
+    ///
+    /// let log = Logger::new().expect("a logger instance");
+    /// let db_log = log.with_domain(Domain::DB).with_fields(&[("pool", "main")]);
+    /// db_log.logm(None, log::Level::Info, "connected");
+    /// ```
+    pub fn with_domain(&self, domain: Domain) -> Self {
+        Self {
+            domain: Some(domain),
+            ..self.clone()
+        }
+    }
+
+    /// Returns a handle logging through the same worker, with the given level for the messages
+    /// logged without level, e.g. through [`Logger::log`] and the [`nslog!`](crate::nslog) macro.
+    pub fn with_level(&self, level: log::Level) -> Self {
+        Self {
+            level: Some(level),
+            ..self.clone()
+        }
+    }
+
+    /// Returns a handle logging through the same worker, adding the given key-value pairs to
+    /// every message, in addition to the pairs of this handle.
+    pub fn with_fields(&self, fields: &dyn log::kv::Source) -> Self {
+        let mut child = self.clone();
+        child.fields.extend(&KeyValues::from_source(fields));
+        child
     }
 
+    /// Level set with [`Logger::with_level`], if any.
+    pub fn default_level(&self) -> Option<log::Level> {
+        self.level
+    }
+
+    /// Returns a builder to configure a new logger.
+    pub fn builder() -> LoggerBuilder {
+        LoggerBuilder::new()
+    }
+
+    /// Creates a logger with the given level filter and destination. When `flush_messages` is
+    /// set, each log call waits for its message to be written before returning.
     pub fn with_options(
-        filter: log::LevelFilter,
+        filter: impl Into<TargetFilter>,
         mode: ConnectionMode,
         flush_messages: bool,
     ) -> Result<Self, Error> {
-        let mut logger = Logger::new()?;
-        logger.filter = filter;
-        logger.flush_messages = flush_messages;
-        logger
-            .message_tx
-            .send(Message::SwitchConnection(mode))
-            .map_err(|_| Error::ChannelNotAvailable)?;
-        Ok(logger)
+        LoggerBuilder::new()
+            .filter(filter)
+            .connection_mode(mode)
+            .flush_messages(flush_messages)
+            .build()
     }
 
+    /// Switches to looking up the desktop viewer through the given Bonjour service.
+    ///
+    /// Fails with the `std-thread` feature, which doesn't support Bonjour.
     pub fn set_bonjour_service(&mut self, service: BonjourServiceType) -> Result<(), Error> {
+        if cfg!(feature = "std-thread") {
+            return Err(Error::InvalidConfiguration(BONJOUR_UNSUPPORTED.to_string()));
+        }
         let connection_mode = ConnectionMode::Bonjour(service);
-        self.message_tx
+        self.core
+            .worker
+            .message_tx
             .send(Message::SwitchConnection(connection_mode))
             .map_err(|_| Error::ChannelNotAvailable)?;
         Ok(())
     }
 
+    /// Switches to a direct connection to the desktop viewer.
     pub fn set_remote_host(
         &self,
         host_name: &str,
@@ -132,24 +509,163 @@ impl Logger {
         use_ssl: bool,
     ) -> Result<(), Error> {
         let connection_mode = ConnectionMode::Tcp(host_name.to_string(), host_port, use_ssl);
-        self.message_tx
+        self.core
+            .worker
+            .message_tx
             .send(Message::SwitchConnection(connection_mode))
             .map_err(|_| Error::ChannelNotAvailable)?;
         Ok(())
     }
 
+    /// Switches to writing the messages to the given file.
     pub fn set_log_file_path(&self, file_path: &str) -> Result<(), Error> {
         let connection_mode = ConnectionMode::File(
             PathBuf::from_str(file_path).map_err(|_| Error::InvalidPath(file_path.to_string()))?,
         );
-        self.message_tx
+        self.core
+            .worker
+            .message_tx
             .send(Message::SwitchConnection(connection_mode))
             .map_err(|_| Error::ChannelNotAvailable)?;
         Ok(())
     }
 
+    /// Replaces the level filters of the records sent through the `log` facade. The maximum level
+    /// of the facade is updated accordingly when this is the global logger, see [`crate::init`].
+    ///
+    /// The filters are shared by all the handles of the logger: calling this on a child handle
+    /// also changes the filters of its parent and siblings.
+    pub fn set_filter(&self, filter: impl Into<TargetFilter>) {
+        let filter = filter.into();
+        if GLOBAL_LOGGER
+            .get()
+            .is_some_and(|logger| Arc::ptr_eq(&logger.core, &self.core))
+        {
+            log::set_max_level(filter.max_level());
+        }
+        *self.core.filter.write().unwrap() = filter;
+    }
+
+    /// Whether the messages of the given level and target pass the level filter, e.g. to skip
+    /// formatting them. The logging macros use the module path as target.
+    pub fn is_enabled(&self, target: &str, level: log::Level) -> bool {
+        level <= self.core.filter.read().unwrap().level_for(target)
+    }
+
+    /// Defines whether each log call waits for its message to be written before returning.
+    ///
+    /// Like [`Logger::set_filter`], this applies to all the handles of the logger, including the
+    /// parent and siblings of a child handle.
     pub fn set_message_flushing(&mut self, flush_each_message: bool) {
-        self.flush_messages = flush_each_message;
+        self.core
+            .flush_messages
+            .store(flush_each_message, Ordering::Relaxed);
+    }
+
+    /// Waits until all the messages logged before this call have been written and flushed to the
+    /// current destination. Returns `false` if that didn't happen before the timeout expired,
+    /// e.g. when the desktop viewer couldn't be reached.
+    pub fn flush(&self, timeout: Duration) -> bool {
+        let signal = Signal::default();
+        if self
+            .core
+            .worker
+            .message_tx
+            .send(Message::Flush(signal.clone()))
+            .is_err()
+        {
+            return false;
+        }
+        signal.wait_timeout(timeout)
+    }
+
+    /// Same as [`Logger::flush`], but returns a future instead of blocking the calling thread.
+    /// The future resolves to `false` if the worker was shut down.
+    ///
+    /// The future has no timeout, and doesn't resolve as long as the desktop viewer can't be
+    /// reached.
+    pub fn flush_async(&self) -> impl Future<Output = bool> + Send + use<> {
+        let signal = Signal::default();
+        let sent = self
+            .core
+            .worker
+            .message_tx
+            .send(Message::Flush(signal.clone()))
+            .is_ok();
+        async move {
+            if sent {
+                signal.wait_async().await;
+            }
+            sent
+        }
+    }
+
+    /// Waits for the messages logged so far to be written, closes the connection, and waits for
+    /// the worker of this logger to stop. Messages logged afterwards are discarded.
+    ///
+    /// Unlike [`crate::shutdown`], this doesn't block the calling thread, hence can be awaited
+    /// from the runtime given to [`Logger::with_runtime_handle`].
+    #[cfg(not(feature = "std-thread"))]
+    pub async fn shutdown_async(&self) {
+        let _ = self
+            .core
+            .worker
+            .message_tx
+            .send(Message::Shutdown(Signal::default()));
+        let Some(tasks) = self.core.worker.tasks.lock().unwrap().take() else {
+            /*
+             * Another caller is already waiting for the worker to stop.
+             */
+            return;
+        };
+        let _ = tasks.worker.await;
+        /*
+         * NOTE the network manager may be waiting for the next Bonjour lookup.
+         */
+        tasks.network_manager.abort();
+        let _ = tasks.network_manager.await;
+    }
+
+    /// Sets a callback receiving the errors that occur while logging, instead of panicking.
+    ///
+    /// The callback is invoked on the thread where the error occurred: the calling thread when a
+    /// message can't be handed to the worker, or the worker for the I/O, connection, SSL and file
+    /// errors. It must not log through this logger.
+    pub fn set_error_handler(&self, handler: impl Fn(&Error) + Send + Sync + 'static) {
+        *self.core.worker.errors.handler.write().unwrap() = Some(ErrorHandler::new(handler));
+    }
+
+    pub(crate) fn set_error_handler_from(&self, handler: Option<ErrorHandler>) {
+        *self.core.worker.errors.handler.write().unwrap() = handler;
+    }
+
+    /// Health of the background worker writing the messages of this logger.
+    pub fn status(&self) -> WorkerStatus {
+        self.core.worker.status.lock().unwrap().clone()
+    }
+
+    /// Counters kept by the background worker writing the messages of this logger.
+    pub fn stats(&self) -> LoggerStats {
+        self.core.worker.stats.snapshot()
+    }
+
+    /// Current state of the connection to the desktop viewer.
+    pub fn connection_state(&self) -> ConnectionStatus {
+        self.core.worker.connection_rx.borrow().clone()
+    }
+
+    /// Returns a receiver notified whenever the state of the connection to the desktop viewer
+    /// changes, or a connection error occurs.
+    pub fn subscribe_connection_state(&self) -> watch::Receiver<ConnectionStatus> {
+        let mut connection_rx = self.core.worker.connection_rx.clone();
+        connection_rx.mark_unchanged();
+        connection_rx
+    }
+
+    /// Number of errors that occurred while logging, e.g. messages that couldn't be sent to the
+    /// worker, or failures of the worker to open the file or to reach the desktop viewer.
+    pub fn error_count(&self) -> u64 {
+        self.core.worker.errors.count.load(Ordering::Relaxed)
     }
 
     fn inner_log(&self, log_message: LogMessage) {
@@ -163,6 +679,64 @@ impl Logger {
         }
     }
 
+    /// Header of the messages logged through this handle, with its domain and fields, and the
+    /// current context for the log, data and image messages.
+    fn header(
+        &self,
+        message_type: LogMessageType,
+        filename: Option<&Path>,
+        line_number: Option<u32>,
+        method: Option<&str>,
+        domain: Option<Domain>,
+        level: log::Level,
+    ) -> LogMessage {
+        let mut log_message = LogMessage::with_header(
+            message_type,
+            filename,
+            line_number,
+            method,
+            domain.or_else(|| self.domain.clone()),
+            level,
+        );
+        if self.core.key_value_parts {
+            if message_type == LogMessageType::Log {
+                context::add_parts(&mut log_message);
+            }
+            self.fields.add_parts(&mut log_message);
+        }
+        log_message
+    }
+
+    /// Domain of the messages with the given target: the domain of this handle, if any, or the
+    /// domain mapped from the target.
+    fn domain_for(&self, target: &str) -> Domain {
+        match &self.domain {
+            Some(domain) => domain.clone(),
+            None => self.core.domain_mapping.domain_for(target),
+        }
+    }
+
+    /// Adds the message text, along with the fields of this handle, the context and the
+    /// key-value pairs, as configured.
+    fn add_text(&self, log_message: &mut LogMessage, mut text: String, key_values: &KeyValues) {
+        let mut text_key_values = self.fields.clone();
+        text_key_values.extend(key_values);
+        if !text_key_values.is_empty() {
+            text_key_values.append_text(&mut text, self.core.key_value_text);
+        }
+        context::current().prepend_text(&mut text, self.core.key_value_text);
+        log_message.add_string(MessagePartKey::Message, &text);
+        if self.core.key_value_parts {
+            key_values.add_parts(log_message);
+        }
+    }
+
+    /// Adds the message text and the key-value pairs, as configured, and sends the message.
+    fn log_key_values(&self, mut log_message: LogMessage, text: String, key_values: &KeyValues) {
+        self.add_text(&mut log_message, text, key_values);
+        self.inner_log(log_message);
+    }
+
     pub fn logl(
         &self,
         filename: Option<&Path>,
@@ -172,7 +746,7 @@ impl Logger {
         level: log::Level,
         message: &str,
     ) {
-        let mut log_message = LogMessage::with_header(
+        let log_message = self.header(
             LogMessageType::Log,
             filename,
             line_number,
@@ -180,16 +754,47 @@ impl Logger {
             domain,
             level,
         );
-        log_message.add_string(MessagePartKey::Message, message);
-        self.inner_log(log_message);
+        self.log_key_values(log_message, message.to_string(), &KeyValues::default());
     }
 
     pub fn logm(&self, domain: Option<Domain>, level: log::Level, message: &str) {
         self.logl(None, None, None, domain, level, message);
     }
 
+    /// Logs a message at the level of this handle, or `Error` if not set.
     pub fn log(&self, message: &str) {
-        self.logm(None, log::Level::Error, message);
+        self.logm(None, self.level.unwrap_or(log::Level::Error), message);
+    }
+
+    /// Same as [`Logger::logm`], but returns a future resolved once the message has been written
+    /// to the current destination, or dropped because the queue was full. Unlike message
+    /// flushing, this doesn't block the calling thread.
+    pub fn log_async(
+        &self,
+        domain: Option<Domain>,
+        level: log::Level,
+        message: &str,
+    ) -> impl Future<Output = ()> + Send + use<> {
+        let mut log_message = self.header(LogMessageType::Log, None, None, None, domain, level);
+        self.add_text(&mut log_message, message.to_string(), &KeyValues::default());
+        /*
+         * NOTE there's no need to wait for the worker to be ready, since the channel keeps the
+         * messages in the meantime.
+         */
+        let signal = Signal::default();
+        if self
+            .core
+            .worker
+            .message_tx
+            .send(Message::AddLog(log_message, Some(signal.clone())))
+            .is_err()
+        {
+            self.core.worker.errors.report(&Error::ChannelNotAvailable);
+            signal.signal();
+        } else {
+            self.core.worker.stats.record_enqueued();
+        }
+        signal.wait_async()
     }
 
     /// Log a mark to the desktop viewer.
@@ -197,6 +802,14 @@ impl Logger {
     /// Marks are important points that you can jump to directly in the desktop viewer. Message is
     /// optional, if null or empty it will be replaced with the current date / time
     pub fn log_mark(&self, message: Option<&str>) {
+        self.inner_log(Self::mark(message))
+    }
+
+    fn mark(message: Option<&str>) -> LogMessage {
+        let mark_message = message.map(|msg| msg.to_string()).unwrap_or_else(|| {
+            let time_now = chrono::Utc::now();
+            time_now.format("%b %-d, %-I:%M:%S").to_string()
+        });
         let mut log_message = LogMessage::with_header(
             LogMessageType::Mark,
             None,
@@ -205,13 +818,39 @@ impl Logger {
             None,
             log::Level::Error,
         );
-
-        let mark_message = message.map(|msg| msg.to_string()).unwrap_or_else(|| {
-            let time_now = chrono::Utc::now();
-            time_now.format("%b %-d, %-I:%M:%S").to_string()
-        });
         log_message.add_string(MessagePartKey::Message, &mark_message);
-        self.inner_log(log_message)
+        log_message
+    }
+
+    /// Logs the message of a panic, followed by a mark, then waits for them to be written within
+    /// [`DEFAULT_FLUSH_TIMEOUT`]. Unlike the other log calls, this never waits for each message,
+    /// whatever [`Logger::set_message_flushing`], so that the panicking thread can't hang when
+    /// the desktop viewer can't be reached. Nothing is waited for on the thread of the worker,
+    /// which couldn't write the messages meanwhile.
+    pub(crate) fn log_panic(
+        &self,
+        filename: Option<&Path>,
+        line_number: Option<u32>,
+        message: &str,
+        mark: &str,
+    ) {
+        self.start_logging_thread_if_needed();
+        let mut log_message = self.header(
+            LogMessageType::Log,
+            filename,
+            line_number,
+            None,
+            None,
+            log::Level::Error,
+        );
+        self.add_text(&mut log_message, message.to_string(), &KeyValues::default());
+        if self.enqueue(log_message, None) && self.enqueue(Self::mark(Some(mark)), None) {
+            #[cfg(feature = "std-thread")]
+            if std::thread::current().id() == self.core.worker.thread_id {
+                return;
+            }
+            self.flush(DEFAULT_FLUSH_TIMEOUT);
+        }
     }
 
     pub fn log_data(
@@ -223,7 +862,7 @@ impl Logger {
         level: log::Level,
         data: &[u8],
     ) {
-        let mut log_message = LogMessage::with_header(
+        let mut log_message = self.header(
             LogMessageType::Log,
             filename,
             line_number,
@@ -244,7 +883,7 @@ impl Logger {
         level: log::Level,
         data: &[u8],
     ) {
-        let mut log_message = LogMessage::with_header(
+        let mut log_message = self.header(
             LogMessageType::Log,
             filename,
             line_number,
@@ -256,23 +895,74 @@ impl Logger {
         self.inner_log(log_message);
     }
 
+    /// Starts a block, which groups the following messages in the desktop viewer until the
+    /// returned guard is dropped. Blocks can be nested.
+    pub fn start_block(
+        &self,
+        filename: Option<&Path>,
+        line_number: Option<u32>,
+        method: Option<&str>,
+        domain: Option<Domain>,
+        level: log::Level,
+        message: &str,
+    ) -> BlockGuard<'_> {
+        let mut log_message = self.header(
+            LogMessageType::BlockStart,
+            filename,
+            line_number,
+            method,
+            domain,
+            level,
+        );
+        log_message.add_string(MessagePartKey::Message, message);
+        self.inner_log(log_message);
+        BlockGuard { logger: Some(self) }
+    }
+
+    fn end_block(&self) {
+        self.inner_log(LogMessage::new(LogMessageType::BlockEnd));
+    }
+
     fn start_logging_thread_if_needed(&self) {
         if DEBUG_LOGGER {
             log::info!("waiting for worker to be ready");
         }
 
-        self.ready_signal.wait();
+        self.core.worker.ready_signal.wait();
 
         if DEBUG_LOGGER {
             log::info!("worker is ready and running");
         }
     }
 
+    /// Sends the message to the worker, and returns whether it was accepted.
+    fn enqueue(&self, log_message: LogMessage, flush_signal: Option<Signal>) -> bool {
+        if self
+            .core
+            .worker
+            .message_tx
+            .send(Message::AddLog(log_message, flush_signal))
+            .is_err()
+        {
+            /*
+             * The worker was shut down.
+             */
+            self.core.worker.errors.report(&Error::ChannelNotAvailable);
+            return false;
+        }
+        self.core.worker.stats.record_enqueued();
+        true
+    }
+
     fn send_and_flush(&self, log_message: LogMessage) {
-        let flush_signal = self.flush_messages.then(Signal::default);
-        self.message_tx
-            .send(Message::AddLog(log_message, flush_signal.clone()))
-            .unwrap();
+        let flush_signal = self
+            .core
+            .flush_messages
+            .load(Ordering::Relaxed)
+            .then(Signal::default);
+        if !self.enqueue(log_message, flush_signal.clone()) {
+            return;
+        }
 
         let Some(signal) = flush_signal else {
             return;
@@ -287,12 +977,26 @@ impl Logger {
     }
 }
 
+/// Ends the block started with [`Logger::start_block`] when dropped.
+///
+/// The default guard doesn't end any block, e.g. for a block skipped by the level filter.
+#[derive(Default)]
+#[must_use = "the block ends when the guard is dropped"]
+pub struct BlockGuard<'a> {
+    logger: Option<&'a Logger>,
+}
+
+impl Drop for BlockGuard<'_> {
+    fn drop(&mut self) {
+        if let Some(logger) = self.logger {
+            logger.end_block();
+        }
+    }
+}
+
 impl log::Log for Logger {
     fn enabled(&self, metadata: &log::Metadata) -> bool {
-        self.filter
-            .to_level()
-            .map(|l| l <= metadata.level())
-            .unwrap_or_default()
+        self.is_enabled(metadata.target(), metadata.level())
     }
 
     fn log(&self, record: &log::Record) {
@@ -300,15 +1004,22 @@ impl log::Log for Logger {
             return;
         }
 
-        self.logl(
+        let log_message = self.header(
+            LogMessageType::Log,
             record.file().map(Path::new),
             record.line(),
-            None,
-            Some(Domain::from_str(record.target()).unwrap()),
+            record.module_path(),
+            Some(self.domain_for(record.target())),
             record.level(),
-            &format!("{}", record.args()),
+        );
+        self.log_key_values(
+            log_message,
+            format!("{}", record.args()),
+            &KeyValues::from_record(record),
         );
     }
 
-    fn flush(&self) {}
+    fn flush(&self) {
+        Logger::flush(self, DEFAULT_FLUSH_TIMEOUT);
+    }
 }
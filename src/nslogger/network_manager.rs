@@ -6,7 +6,7 @@ use tokio::{
     time::{sleep, timeout},
 };
 
-use crate::nslogger::{Message, DEBUG_LOGGER};
+use crate::nslogger::{channel, stats::SharedStats, BonjourServiceType, Message, DEBUG_LOGGER};
 
 pub enum BonjourServiceStatus {
     ServiceFound(String, String, u16, bool),
@@ -14,27 +14,25 @@ pub enum BonjourServiceStatus {
     Unresolved,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub enum BonjourServiceType {
-    /// Service type
-    Custom(String, bool),
-    /// Defines whether to use SSL
-    Default(bool),
-}
-
 pub struct NetworkManager {
     command_rx: mpsc::UnboundedReceiver<BonjourServiceType>,
-    message_tx: mpsc::UnboundedSender<Message>,
+    /*
+     * NOTE the sender is weak so that the worker stops once all its loggers are dropped.
+     */
+    message_tx: channel::WeakUnboundedSender<Message>,
+    stats: SharedStats,
 }
 
 impl NetworkManager {
     pub fn new(
         command_rx: mpsc::UnboundedReceiver<BonjourServiceType>,
-        message_tx: mpsc::UnboundedSender<Message>,
+        message_tx: channel::WeakUnboundedSender<Message>,
+        stats: SharedStats,
     ) -> NetworkManager {
         NetworkManager {
             command_rx,
             message_tx,
+            stats,
         }
     }
 
@@ -59,11 +57,29 @@ impl NetworkManager {
                         if DEBUG_LOGGER {
                             log::info!("found Bonjour service {bonjour_service_name}");
                         }
-                        self.message_tx
+                        let Some(message_tx) = self.message_tx.upgrade() else {
+                            /*
+                             * All the loggers were dropped.
+                             */
+                            return Ok(());
+                        };
+                        if message_tx
                             .send(Message::ConnectToBonjourService(host, port, use_ssl))
-                            .unwrap();
+                            .is_err()
+                        {
+                            /*
+                             * The worker was shut down.
+                             */
+                            return Ok(());
+                        }
                         is_connected = true;
                     }
+                    _ if self.command_rx.is_closed() => {
+                        /*
+                         * The worker was shut down.
+                         */
+                        return Ok(());
+                    }
                     _ => {
                         if DEBUG_LOGGER {
                             log::info!("couldn't resolve Bonjour. Will retry in a few seconds");
@@ -88,6 +104,7 @@ impl NetworkManager {
         if DEBUG_LOGGER {
             log::info!("setting up Bonjour");
         }
+        self.stats.record_bonjour_lookup();
         let (service_name, use_ssl) = match service_type {
             BonjourServiceType::Custom(name, use_ssl) => (name.as_str(), *use_ssl),
             BonjourServiceType::Default(use_ssl) if *use_ssl => ("_nslogger-ssl._tcp.", true),
@@ -0,0 +1,160 @@
+use std::{fmt, path::Path, str::FromStr, sync::Arc};
+
+use slog::{Drain, Key, OwnedKVList, Record, Serializer, KV};
+
+use crate::nslogger::{key_values::Value, Domain, KeyValues, LogMessageType, Logger};
+
+/// Sends the records of the `slog` crate through a [`Logger`], interleaved with the messages
+/// logged through the logger itself.
+///
+/// The tag of the records becomes their domain, and the module path is mapped to a domain when
+/// the tag is empty. The key-value pairs of the record and of the `slog` logger chain are sent as
+/// with the `log` facade.
+///
+/// ```rust,no_run
+/// use slog::{info, o, Drain};
+///
+/// let log = nslogger::Logger::builder()
+///     .filter(log::LevelFilter::Info)
+///     .build()
+///     .expect("a valid configuration");
+/// let root = slog::Logger::root(nslogger::NSLoggerDrain::new(log).fuse(), o!("version" => "1.0"));
+/// info!(root, # "DB", "connected"; "pool_size" => 4);
+/// ```
+pub struct NSLoggerDrain {
+    logger: Arc<Logger>,
+}
+
+impl NSLoggerDrain {
+    pub fn new(logger: impl Into<Arc<Logger>>) -> Self {
+        Self {
+            logger: logger.into(),
+        }
+    }
+}
+
+impl Drain for NSLoggerDrain {
+    type Ok = ();
+    type Err = slog::Never;
+
+    fn log(&self, record: &Record, values: &OwnedKVList) -> Result<(), slog::Never> {
+        let level = level(record.level());
+        if !self.logger.is_enabled(record.module(), level) {
+            return Ok(());
+        }
+        let domain = match record.tag() {
+            "" => self.logger.domain_for(record.module()),
+            tag => Domain::from_str(tag).unwrap(),
+        };
+        let function = match record.function() {
+            "" => record.module(),
+            function => function,
+        };
+        let log_message = self.logger.header(
+            LogMessageType::Log,
+            Some(Path::new(record.file())),
+            Some(record.line()),
+            Some(function),
+            Some(domain),
+            level,
+        );
+
+        /*
+         * slog serializes the pairs of the record, then the pairs of the logger chain from the
+         * innermost logger, each group in reverse order: the pairs are replayed backwards so that
+         * the innermost ones take precedence.
+         */
+        let mut pairs = Pairs::default();
+        let _ = record.kv().serialize(record, &mut pairs);
+        let _ = values.serialize(record, &mut pairs);
+        let mut key_values = KeyValues::default();
+        for (key, value) in pairs.0.into_iter().rev() {
+            key_values.insert(key, value);
+        }
+        self.logger
+            .log_key_values(log_message, format!("{}", record.msg()), &key_values);
+        Ok(())
+    }
+}
+
+fn level(level: slog::Level) -> log::Level {
+    match level {
+        slog::Level::Critical | slog::Level::Error => log::Level::Error,
+        slog::Level::Warning => log::Level::Warn,
+        slog::Level::Info => log::Level::Info,
+        slog::Level::Debug => log::Level::Debug,
+        slog::Level::Trace => log::Level::Trace,
+    }
+}
+
+/// Key-value pairs in the order serialized by slog.
+#[derive(Default)]
+struct Pairs(Vec<(Key, Value)>);
+
+impl Serializer for Pairs {
+    fn emit_arguments(&mut self, key: Key, val: &fmt::Arguments) -> slog::Result {
+        self.0.push((key, Value::Str(val.to_string())));
+        Ok(())
+    }
+
+    fn emit_str(&mut self, key: Key, val: &str) -> slog::Result {
+        self.0.push((key, Value::Str(val.to_string())));
+        Ok(())
+    }
+
+    fn emit_bool(&mut self, key: Key, val: bool) -> slog::Result {
+        self.0.push((key, Value::Bool(val)));
+        Ok(())
+    }
+
+    fn emit_f64(&mut self, key: Key, val: f64) -> slog::Result {
+        self.0.push((key, Value::Float(val)));
+        Ok(())
+    }
+
+    fn emit_f32(&mut self, key: Key, val: f32) -> slog::Result {
+        self.emit_f64(key, val.into())
+    }
+
+    fn emit_i64(&mut self, key: Key, val: i64) -> slog::Result {
+        self.0.push((key, Value::Int(val)));
+        Ok(())
+    }
+
+    fn emit_i32(&mut self, key: Key, val: i32) -> slog::Result {
+        self.emit_i64(key, val.into())
+    }
+
+    fn emit_i16(&mut self, key: Key, val: i16) -> slog::Result {
+        self.emit_i64(key, val.into())
+    }
+
+    fn emit_i8(&mut self, key: Key, val: i8) -> slog::Result {
+        self.emit_i64(key, val.into())
+    }
+
+    fn emit_isize(&mut self, key: Key, val: isize) -> slog::Result {
+        self.emit_i64(key, val as i64)
+    }
+
+    fn emit_u64(&mut self, key: Key, val: u64) -> slog::Result {
+        self.0.push((key, Value::UInt(val)));
+        Ok(())
+    }
+
+    fn emit_u32(&mut self, key: Key, val: u32) -> slog::Result {
+        self.emit_u64(key, val.into())
+    }
+
+    fn emit_u16(&mut self, key: Key, val: u16) -> slog::Result {
+        self.emit_u64(key, val.into())
+    }
+
+    fn emit_u8(&mut self, key: Key, val: u8) -> slog::Result {
+        self.emit_u64(key, val.into())
+    }
+
+    fn emit_usize(&mut self, key: Key, val: usize) -> slog::Result {
+        self.emit_u64(key, val as u64)
+    }
+}
@@ -0,0 +1,155 @@
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+/// Snapshot of the counters kept by the background worker.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct LoggerStats {
+    /// Messages sent to the worker.
+    pub enqueued: u64,
+    /// Messages written to the destination.
+    pub written: u64,
+    /// Messages dropped because the queue was full.
+    pub dropped: u64,
+    /// Messages neither written nor dropped yet.
+    pub pending: u64,
+    /// Bytes written to the destination, including the client info messages.
+    pub bytes_written: u64,
+    /// Connection attempts following the loss of a connection.
+    pub reconnect_attempts: u64,
+    /// Bonjour lookups for the desktop viewer.
+    pub bonjour_lookups: u64,
+    /// Time elapsed since the last successful write, if any.
+    pub since_last_write: Option<Duration>,
+    /// Largest number of messages waiting in the queue of the worker.
+    pub queue_high_water_mark: u64,
+    /// Identifier of the logger, unique within the process, with which its metrics are labelled.
+    pub logger_id: u64,
+}
+
+/// Counters updated by the loggers, the worker and the network manager. They are also published
+/// through the `metrics` facade when the `metrics` feature is enabled, labelled with the
+/// identifier of the logger (`logger="1"`), as each logger has its own worker.
+#[derive(Debug)]
+pub struct WorkerStats {
+    logger_id: u64,
+    enqueued: AtomicU64,
+    written: AtomicU64,
+    dropped: AtomicU64,
+    bytes_written: AtomicU64,
+    reconnect_attempts: AtomicU64,
+    bonjour_lookups: AtomicU64,
+    queue_high_water_mark: AtomicU64,
+    last_write: Mutex<Option<Instant>>,
+}
+
+pub type SharedStats = Arc<WorkerStats>;
+
+impl Default for WorkerStats {
+    fn default() -> Self {
+        static NEXT_LOGGER_ID: AtomicU64 = AtomicU64::new(1);
+        Self {
+            logger_id: NEXT_LOGGER_ID.fetch_add(1, Ordering::Relaxed),
+            enqueued: AtomicU64::default(),
+            written: AtomicU64::default(),
+            dropped: AtomicU64::default(),
+            bytes_written: AtomicU64::default(),
+            reconnect_attempts: AtomicU64::default(),
+            bonjour_lookups: AtomicU64::default(),
+            queue_high_water_mark: AtomicU64::default(),
+            last_write: Mutex::default(),
+        }
+    }
+}
+
+impl WorkerStats {
+    pub fn record_enqueued(&self) {
+        self.enqueued.fetch_add(1, Ordering::Relaxed);
+        #[cfg(feature = "metrics")]
+        metrics::counter!("nslogger.messages.enqueued", &self.labels()).increment(1);
+        self.publish_pending();
+    }
+
+    pub fn record_written(&self, bytes: usize, is_log_message: bool) {
+        self.bytes_written
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+        *self.last_write.lock().unwrap() = Some(Instant::now());
+        #[cfg(feature = "metrics")]
+        metrics::counter!("nslogger.bytes.written", &self.labels()).increment(bytes as u64);
+        if is_log_message {
+            self.written.fetch_add(1, Ordering::Relaxed);
+            #[cfg(feature = "metrics")]
+            metrics::counter!("nslogger.messages.written", &self.labels()).increment(1);
+            self.publish_pending();
+        }
+    }
+
+    pub fn record_dropped(&self) {
+        self.dropped.fetch_add(1, Ordering::Relaxed);
+        #[cfg(feature = "metrics")]
+        metrics::counter!("nslogger.messages.dropped", &self.labels()).increment(1);
+        self.publish_pending();
+    }
+
+    pub fn record_reconnect_attempt(&self) {
+        self.reconnect_attempts.fetch_add(1, Ordering::Relaxed);
+        #[cfg(feature = "metrics")]
+        metrics::counter!("nslogger.reconnect_attempts", &self.labels()).increment(1);
+    }
+
+    #[cfg(not(feature = "std-thread"))]
+    pub fn record_bonjour_lookup(&self) {
+        self.bonjour_lookups.fetch_add(1, Ordering::Relaxed);
+        #[cfg(feature = "metrics")]
+        metrics::counter!("nslogger.bonjour_lookups", &self.labels()).increment(1);
+    }
+
+    pub fn record_queue_length(&self, length: usize) {
+        self.queue_high_water_mark
+            .fetch_max(length as u64, Ordering::Relaxed);
+        #[cfg(feature = "metrics")]
+        metrics::gauge!("nslogger.queue.high_water_mark", &self.labels())
+            .set(self.queue_high_water_mark.load(Ordering::Relaxed) as f64);
+    }
+
+    #[cfg(feature = "metrics")]
+    fn labels(&self) -> [(&'static str, String); 1] {
+        [("logger", self.logger_id.to_string())]
+    }
+
+    fn pending(&self) -> u64 {
+        let processed = self.written.load(Ordering::Relaxed) + self.dropped.load(Ordering::Relaxed);
+        self.enqueued
+            .load(Ordering::Relaxed)
+            .saturating_sub(processed)
+    }
+
+    fn publish_pending(&self) {
+        #[cfg(feature = "metrics")]
+        metrics::gauge!("nslogger.messages.pending", &self.labels()).set(self.pending() as f64);
+    }
+
+    pub fn snapshot(&self) -> LoggerStats {
+        LoggerStats {
+            enqueued: self.enqueued.load(Ordering::Relaxed),
+            written: self.written.load(Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+            pending: self.pending(),
+            bytes_written: self.bytes_written.load(Ordering::Relaxed),
+            reconnect_attempts: self.reconnect_attempts.load(Ordering::Relaxed),
+            bonjour_lookups: self.bonjour_lookups.load(Ordering::Relaxed),
+            since_last_write: self
+                .last_write
+                .lock()
+                .unwrap()
+                .map(|instant| instant.elapsed()),
+            queue_high_water_mark: self.queue_high_water_mark.load(Ordering::Relaxed),
+            logger_id: self.logger_id,
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use std::{
+    any::Any,
+    panic::AssertUnwindSafe,
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+#[cfg(not(feature = "std-thread"))]
+use futures::FutureExt;
+
+use crate::nslogger::{Error, LogWorker, DEBUG_LOGGER};
+
+/// Delay before restarting a failed worker.
+const RESTART_DELAY: Duration = Duration::from_secs(1);
+
+/// State of the background worker that writes the log messages.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum WorkerState {
+    /// The worker wasn't started yet.
+    #[default]
+    Starting,
+    /// The worker is processing the log messages.
+    Running,
+    /// The worker failed, and will be restarted shortly. Queued messages are kept.
+    Restarting,
+    /// The worker was shut down, and won't process any more messages.
+    Stopped,
+}
+
+/// Health of the background worker that writes the log messages.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct WorkerStatus {
+    pub state: WorkerState,
+    /// Number of times the worker was restarted after a failure.
+    pub restarts: u32,
+    /// Description of the last failure, if any.
+    pub last_error: Option<String>,
+}
+
+pub type SharedWorkerStatus = Arc<Mutex<WorkerStatus>>;
+
+/// Runs the worker until it is shut down, restarting it whenever it fails.
+#[cfg(not(feature = "std-thread"))]
+pub async fn supervise(mut worker: LogWorker, status: SharedWorkerStatus) {
+    loop {
+        status.lock().unwrap().state = WorkerState::Running;
+        let outcome = AssertUnwindSafe(worker.run()).catch_unwind().await;
+        let Some(failure) = failure(outcome) else {
+            break;
+        };
+        record_failure(&status, failure);
+        worker.disconnect();
+        tokio::time::sleep(RESTART_DELAY).await;
+    }
+    status.lock().unwrap().state = WorkerState::Stopped;
+}
+
+/// Runs the worker until it is shut down, restarting it whenever it fails.
+#[cfg(feature = "std-thread")]
+pub fn supervise(mut worker: LogWorker, status: SharedWorkerStatus) {
+    loop {
+        status.lock().unwrap().state = WorkerState::Running;
+        let outcome = std::panic::catch_unwind(AssertUnwindSafe(|| worker.run()));
+        let Some(failure) = failure(outcome) else {
+            break;
+        };
+        record_failure(&status, failure);
+        worker.disconnect();
+        thread::sleep(RESTART_DELAY);
+    }
+    status.lock().unwrap().state = WorkerState::Stopped;
+}
+
+/// Describes why the worker stopped, unless it was shut down.
+fn failure(outcome: thread::Result<Result<(), Error>>) -> Option<String> {
+    match outcome {
+        Ok(Ok(())) => None,
+        Ok(Err(err)) => Some(err.to_string()),
+        Err(panic) => Some(panic_message(panic.as_ref())),
+    }
+}
+
+fn record_failure(status: &SharedWorkerStatus, failure: String) {
+    /*
+     * NOTE the failure isn't reported through the `log` facade, which may be bound to this very
+     * worker, but written to stderr, only when it differs from the previous one so that a
+     * restart loop doesn't flood it.
+     */
+    if DEBUG_LOGGER {
+        log::warn!("worker failed: {failure}");
+    }
+    let mut status = status.lock().unwrap();
+    if status.last_error.as_ref() != Some(&failure) {
+        eprintln!("nslogger: worker failed: {failure}");
+    }
+    status.state = WorkerState::Restarting;
+    status.restarts += 1;
+    status.last_error = Some(failure);
+}
+
+fn panic_message(panic: &(dyn Any + Send)) -> String {
+    panic
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| panic.downcast_ref::<String>().cloned())
+        .map(|message| format!("panicked: {message}"))
+        .unwrap_or_else(|| "panicked".to_string())
+}
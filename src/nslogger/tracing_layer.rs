@@ -0,0 +1,238 @@
+use std::{fmt, path::Path, sync::Arc, time::Instant};
+
+use tracing::{
+    field::{Field, Visit},
+    span, Event, Metadata, Subscriber,
+};
+use tracing_subscriber::{layer::Context, registry::LookupSpan, Layer};
+
+use crate::nslogger::{key_values::Value, KeyValues, LogMessageType, Logger};
+
+/// Sends the events and spans of the `tracing` crate through a [`Logger`].
+///
+/// Events become log messages, with the fields of their parent spans. Spans become blocks, which
+/// nest in the desktop viewer: a block starts each time a span is entered and ends when it is
+/// exited. The duration of each span is logged when it closes.
+///
+/// The level filter and the domain mapping of the logger apply to the targets of the events and
+/// spans.
+///
+/// ```rust,no_run
+/// use tracing_subscriber::layer::SubscriberExt;
+///
+/// let log = nslogger::Logger::builder()
+///     .filter(log::LevelFilter::Debug)
+///     .build()
+///     .expect("a valid configuration");
+/// let subscriber = tracing_subscriber::registry().with(nslogger::NSLoggerLayer::new(log));
+/// tracing::subscriber::set_global_default(subscriber).expect("no other subscriber");
+/// ```
+pub struct NSLoggerLayer {
+    logger: Arc<Logger>,
+}
+
+impl NSLoggerLayer {
+    pub fn new(logger: impl Into<Arc<Logger>>) -> Self {
+        Self {
+            logger: logger.into(),
+        }
+    }
+
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.logger.is_enabled(metadata.target(), level(metadata))
+    }
+
+    fn log_with_header(
+        &self,
+        message_type: LogMessageType,
+        metadata: &Metadata,
+        text: String,
+        key_values: &KeyValues,
+    ) {
+        let log_message = self.logger.header(
+            message_type,
+            metadata.file().map(Path::new),
+            metadata.line(),
+            metadata.module_path(),
+            Some(self.logger.domain_for(metadata.target())),
+            level(metadata),
+        );
+        self.logger.log_key_values(log_message, text, key_values);
+    }
+}
+
+/// Fields recorded on a span, and inherited by the events in its scope.
+struct SpanFields {
+    key_values: KeyValues,
+    created: Instant,
+}
+
+impl<S> Layer<S> for NSLoggerLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &span::Attributes, id: &span::Id, ctx: Context<S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut fields = Fields::default();
+        attrs.record(&mut fields);
+        span.extensions_mut().insert(SpanFields {
+            key_values: fields.into_key_values(),
+            created: Instant::now(),
+        });
+    }
+
+    fn on_record(&self, id: &span::Id, values: &span::Record, ctx: Context<S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut fields = Fields::default();
+        values.record(&mut fields);
+        if let Some(span_fields) = span.extensions_mut().get_mut::<SpanFields>() {
+            span_fields.key_values.extend(&fields.into_key_values());
+        }
+    }
+
+    fn on_event(&self, event: &Event, ctx: Context<S>) {
+        let metadata = event.metadata();
+        if !self.enabled(metadata) {
+            return;
+        }
+        let mut key_values = KeyValues::default();
+        if let Some(scope) = ctx.event_scope(event) {
+            for span in scope.from_root() {
+                if let Some(span_fields) = span.extensions().get::<SpanFields>() {
+                    key_values.extend(&span_fields.key_values);
+                }
+            }
+        }
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        key_values.extend(&fields.key_values);
+        self.log_with_header(
+            LogMessageType::Log,
+            metadata,
+            fields.message.unwrap_or_default(),
+            &key_values,
+        );
+    }
+
+    fn on_enter(&self, id: &span::Id, ctx: Context<S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        if !self.enabled(span.metadata()) {
+            return;
+        }
+        let extensions = span.extensions();
+        let key_values = extensions
+            .get::<SpanFields>()
+            .map(|span_fields| &span_fields.key_values);
+        self.log_with_header(
+            LogMessageType::BlockStart,
+            span.metadata(),
+            span.name().to_string(),
+            key_values.unwrap_or(&KeyValues::default()),
+        );
+    }
+
+    fn on_exit(&self, id: &span::Id, ctx: Context<S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        if self.enabled(span.metadata()) {
+            self.logger.end_block();
+        }
+    }
+
+    fn on_close(&self, id: span::Id, ctx: Context<S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        if !self.enabled(span.metadata()) {
+            return;
+        }
+        let Some(created) = span
+            .extensions()
+            .get::<SpanFields>()
+            .map(|span_fields| span_fields.created)
+        else {
+            return;
+        };
+        let duration = created.elapsed();
+        let mut key_values = KeyValues::default();
+        key_values.insert(
+            "duration_us",
+            Value::UInt(duration.as_micros().try_into().unwrap_or(u64::MAX)),
+        );
+        self.log_with_header(
+            LogMessageType::Log,
+            span.metadata(),
+            format!("{} closed after {duration:?}", span.name()),
+            &key_values,
+        );
+    }
+}
+
+fn level(metadata: &Metadata) -> log::Level {
+    match *metadata.level() {
+        tracing::Level::ERROR => log::Level::Error,
+        tracing::Level::WARN => log::Level::Warn,
+        tracing::Level::INFO => log::Level::Info,
+        tracing::Level::DEBUG => log::Level::Debug,
+        tracing::Level::TRACE => log::Level::Trace,
+    }
+}
+
+/// Collects the fields of an event or a span, keeping the `message` field apart.
+#[derive(Default)]
+struct Fields {
+    message: Option<String>,
+    key_values: KeyValues,
+}
+
+impl Fields {
+    fn into_key_values(mut self) -> KeyValues {
+        if let Some(message) = self.message.take() {
+            self.key_values.insert("message", Value::Str(message));
+        }
+        self.key_values
+    }
+}
+
+impl Visit for Fields {
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.key_values.insert(field.name(), Value::Int(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.key_values.insert(field.name(), Value::UInt(value));
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.key_values.insert(field.name(), Value::Float(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.key_values.insert(field.name(), Value::Bool(value));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message = Some(value.to_string());
+        } else {
+            self.key_values
+                .insert(field.name(), Value::Str(value.to_string()));
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.message = Some(format!("{value:?}"));
+        } else {
+            self.key_values
+                .insert(field.name(), Value::Str(format!("{value:?}")));
+        }
+    }
+}
@@ -0,0 +1,128 @@
+#[cfg(not(feature = "std-thread"))]
+use std::{
+    io,
+    sync::{Arc, Mutex},
+};
+
+use tokio::sync::watch;
+#[cfg(not(feature = "std-thread"))]
+use tokio::{runtime::Handle, sync::mpsc, task::JoinHandle};
+
+#[cfg(not(feature = "std-thread"))]
+use crate::nslogger::network_manager;
+#[cfg(feature = "std-thread")]
+use crate::nslogger::Error;
+use crate::nslogger::{
+    channel,
+    log_worker::ConnectionStatus,
+    stats::SharedStats,
+    supervisor::{self, SharedWorkerStatus},
+    LogWorker, Message, SharedErrors, Signal, DEBUG_LOGGER,
+};
+
+/// Tasks running a log worker and its network manager.
+#[cfg(not(feature = "std-thread"))]
+#[derive(Debug)]
+pub struct WorkerTasks {
+    pub worker: JoinHandle<()>,
+    pub network_manager: JoinHandle<io::Result<()>>,
+}
+
+/// Endpoints of a log worker, shared by all the loggers writing to the same destination.
+///
+/// The worker stops once all its handles are dropped.
+#[derive(Debug, Clone)]
+pub struct WorkerHandle {
+    pub ready_signal: Signal,
+    pub message_tx: channel::UnboundedSender<Message>,
+    pub status: SharedWorkerStatus,
+    pub connection_rx: watch::Receiver<ConnectionStatus>,
+    pub stats: SharedStats,
+    pub errors: SharedErrors,
+    /// Thread running the worker.
+    #[cfg(feature = "std-thread")]
+    pub thread_id: std::thread::ThreadId,
+    /// Taken by the first caller waiting for the worker to stop.
+    #[cfg(not(feature = "std-thread"))]
+    pub tasks: Arc<Mutex<Option<WorkerTasks>>>,
+}
+
+impl WorkerHandle {
+    /// Spawns a new log worker, along with its network manager, on the given runtime. The runtime
+    /// must have the IO and time drivers enabled.
+    #[cfg(not(feature = "std-thread"))]
+    pub fn spawn(runtime: &Handle) -> Self {
+        if DEBUG_LOGGER {
+            log::info!("spawning log worker");
+        }
+        let (command_tx, command_rx) = mpsc::unbounded_channel();
+        let (message_tx, message_rx) = channel::unbounded_channel();
+        let ready_signal = Signal::default();
+        let status = SharedWorkerStatus::default();
+        let (connection_tx, connection_rx) = watch::channel(ConnectionStatus::default());
+        let stats = SharedStats::default();
+        let errors = SharedErrors::default();
+
+        let mut network_manager =
+            network_manager::NetworkManager::new(command_rx, message_tx.downgrade(), stats.clone());
+        let network_manager = runtime.spawn(async move { network_manager.run().await });
+        let worker = LogWorker::new(
+            command_tx,
+            message_rx,
+            ready_signal.clone(),
+            connection_tx,
+            stats.clone(),
+            errors.clone(),
+        );
+        let worker = runtime.spawn(supervisor::supervise(worker, status.clone()));
+        Self {
+            ready_signal,
+            message_tx,
+            status,
+            connection_rx,
+            stats,
+            errors,
+            tasks: Arc::new(Mutex::new(Some(WorkerTasks {
+                worker,
+                network_manager,
+            }))),
+        }
+    }
+
+    /// Spawns a new log worker on a dedicated thread.
+    #[cfg(feature = "std-thread")]
+    pub fn spawn() -> Result<Self, Error> {
+        if DEBUG_LOGGER {
+            log::info!("spawning log worker thread");
+        }
+        let (message_tx, message_rx) = channel::unbounded_channel();
+        let ready_signal = Signal::default();
+        let status = SharedWorkerStatus::default();
+        let (connection_tx, connection_rx) = watch::channel(ConnectionStatus::default());
+        let stats = SharedStats::default();
+        let errors = SharedErrors::default();
+
+        let worker = LogWorker::new(
+            message_rx,
+            ready_signal.clone(),
+            connection_tx,
+            stats.clone(),
+            errors.clone(),
+        );
+        let worker_status = status.clone();
+        let thread_id = std::thread::Builder::new()
+            .name("nslogger".to_string())
+            .spawn(move || supervisor::supervise(worker, worker_status))?
+            .thread()
+            .id();
+        Ok(Self {
+            ready_signal,
+            message_tx,
+            status,
+            connection_rx,
+            stats,
+            errors,
+            thread_id,
+        })
+    }
+}
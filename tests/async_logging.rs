@@ -0,0 +1,52 @@
+use std::{fs::File, io::Read};
+
+use log::Level;
+use nslogger::{Domain, KeyValueText, Logger};
+use tempfile::NamedTempFile;
+
+#[tokio::test]
+async fn resolves_on_delivery() {
+    let tempfile = NamedTempFile::new().expect("temp file");
+    let file_path = tempfile.into_temp_path();
+    let log = Logger::builder()
+        .log_file_path(file_path.to_path_buf())
+        .build()
+        .expect("logger instance");
+    log.log_async(Some(Domain::App), Level::Warn, "delivered message")
+        .await;
+    assert_eq!(1, log.stats().written);
+    log.logm(Some(Domain::App), Level::Warn, "flushed message");
+    assert!(log.flush_async().await);
+
+    let mut buf = Vec::new();
+    File::open(&file_path)
+        .expect("file should exist")
+        .read_to_end(&mut buf)
+        .expect("file read");
+    assert!(buf.ends_with(b"flushed message"));
+}
+
+#[tokio::test]
+async fn logs_like_logm() {
+    let tempfile = NamedTempFile::new().expect("temp file");
+    let file_path = tempfile.into_temp_path();
+    let log = Logger::builder()
+        .log_file_path(file_path.to_path_buf())
+        .key_value_text(KeyValueText::Logfmt)
+        .build()
+        .expect("logger instance");
+    let db_log = log.with_domain(Domain::DB).with_fields(&[("pool", "main")]);
+    nslogger::with_context_async(&[("request_id", 42)], async {
+        db_log.log_async(None, Level::Warn, "async message").await;
+    })
+    .await;
+
+    let mut buf = Vec::new();
+    File::open(&file_path)
+        .expect("file should exist")
+        .read_to_end(&mut buf)
+        .expect("file read");
+    let contents = String::from_utf8_lossy(&buf);
+    assert!(contents.contains("DB"));
+    assert!(contents.ends_with("[request_id=42] async message pool=main"));
+}
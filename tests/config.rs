@@ -0,0 +1,32 @@
+#![cfg(feature = "config")]
+
+use std::{env, fs::File, io::Read, io::Write, time::Duration};
+
+use log::LevelFilter;
+use tempfile::NamedTempFile;
+
+#[test]
+fn initializes_from_file() {
+    let file_path = NamedTempFile::new().expect("temp file").into_temp_path();
+    let mut config = NamedTempFile::new().expect("temp file");
+    writeln!(
+        config,
+        "filename = {:?}\nlevel = \"info\"\nclient_name = \"config_test\"",
+        file_path.to_str().unwrap()
+    )
+    .expect("config written");
+    unsafe {
+        env::set_var("NSLOG_LEVEL", "debug");
+    }
+    nslogger::init_from_file(config.path()).expect("global logger");
+    assert_eq!(LevelFilter::Debug, log::max_level());
+
+    log::debug!("configured from a file");
+    assert!(nslogger::shutdown(Duration::from_secs(5)));
+    let mut buf = Vec::new();
+    File::open(&file_path)
+        .expect("file should exist")
+        .read_to_end(&mut buf)
+        .expect("file read");
+    assert!(buf.ends_with(b"configured from a file"));
+}
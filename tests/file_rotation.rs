@@ -0,0 +1,39 @@
+use std::{fs, path::PathBuf, time::Duration};
+
+use log::Level;
+use nslogger::{Domain, Logger};
+
+#[test]
+fn rotates_log_file() {
+    let directory = tempfile::tempdir().expect("temp directory");
+    let file_path = directory.path().join("output.rawnsloggerdata");
+    let rotated_path = |index: usize| PathBuf::from(format!("{}.{index}", file_path.display()));
+    let log = Logger::builder()
+        .log_file_path(&file_path)
+        .rotate_log_file(1024, 2)
+        .client_name("rotation_test")
+        .build()
+        .expect("logger instance");
+    for index in 0..50 {
+        log.logm(
+            Some(Domain::App),
+            Level::Warn,
+            &format!("message {index:02} {}", "x".repeat(100)),
+        );
+    }
+    assert!(log.flush(Duration::from_secs(5)));
+
+    /*
+     * Each file starts with the client info, and the oldest files are removed.
+     */
+    let current = fs::read(&file_path).expect("current file read");
+    assert!(current.ends_with(format!("message 49 {}", "x".repeat(100)).as_bytes()));
+    for path in [file_path.clone(), rotated_path(1), rotated_path(2)] {
+        let contents = fs::read(&path).expect("log file read");
+        assert!(contents.len() <= 1024, "{path:?}");
+        let contents = String::from_utf8_lossy(&contents);
+        assert_eq!(1, contents.matches("rotation_test").count(), "{path:?}");
+        assert!(!contents.contains("message 00"), "{path:?}");
+    }
+    assert!(!rotated_path(3).exists());
+}
@@ -0,0 +1,31 @@
+use std::env;
+
+use log::{Level, LevelFilter, Log, Metadata};
+use nslogger::TargetFilter;
+use tempfile::NamedTempFile;
+
+fn enabled(target: &str, level: Level) -> bool {
+    let logger = nslogger::logger().expect("global logger");
+    logger.enabled(&Metadata::builder().target(target).level(level).build())
+}
+
+#[test]
+fn updates_max_level() {
+    let file_path = NamedTempFile::new().expect("temp file").into_temp_path();
+    unsafe {
+        env::set_var("NSLOG_FILENAME", file_path.to_str().unwrap());
+        env::set_var("NSLOG_LEVEL", "warn,my_app::db=trace,hyper=off");
+    }
+    nslogger::init().expect("global logger");
+    assert_eq!(LevelFilter::Trace, log::max_level());
+    assert!(enabled("my_app::db::pool", Level::Trace));
+    assert!(!enabled("my_app::web", Level::Info));
+    assert!(!enabled("hyper::client", Level::Error));
+
+    nslogger::logger().expect("global logger").set_filter(
+        TargetFilter::from(LevelFilter::Error).directive("my_app::web", LevelFilter::Info),
+    );
+    assert_eq!(LevelFilter::Info, log::max_level());
+    assert!(enabled("my_app::web", Level::Info));
+    assert!(!enabled("my_app::db", Level::Warn));
+}
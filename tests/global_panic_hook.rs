@@ -0,0 +1,34 @@
+use std::{env, fs::File, io::Read, thread};
+
+use tempfile::NamedTempFile;
+
+#[test]
+fn logs_panics_through_global_logger() {
+    let file_path = NamedTempFile::new().expect("temp file").into_temp_path();
+    unsafe {
+        env::set_var("NSLOG_FILENAME", file_path.to_str().unwrap());
+    }
+    nslogger::init().expect("global logger");
+    log::warn!("logged before the panic");
+    nslogger::install_panic_hook().expect("panic hook");
+
+    let result = thread::Builder::new()
+        .name("panicking-thread".to_string())
+        .spawn(|| panic!("something went wrong"))
+        .unwrap()
+        .join();
+    assert!(result.is_err());
+
+    /*
+     * The hook shares the connection of the global logger instead of truncating the file.
+     */
+    let mut buf = Vec::new();
+    File::open(&file_path)
+        .expect("file should exist")
+        .read_to_end(&mut buf)
+        .expect("file read");
+    let contents = String::from_utf8_lossy(&buf);
+    assert!(contents.contains("logged before the panic"));
+    assert!(contents.contains("something went wrong"));
+    assert!(contents.ends_with("panic in thread 'panicking-thread'"));
+}
@@ -0,0 +1,33 @@
+use std::{fs::File, io::Read, thread};
+
+use nslogger::Logger;
+use tempfile::NamedTempFile;
+
+#[test]
+fn logs_panics() {
+    let tempfile = NamedTempFile::new().expect("temp file");
+    let file_path = tempfile.into_temp_path();
+    let log = Logger::builder()
+        .log_file_path(file_path.to_path_buf())
+        .build()
+        .expect("logger instance");
+    nslogger::install_panic_hook_with(log);
+
+    let result = thread::Builder::new()
+        .name("panicking-thread".to_string())
+        .spawn(|| panic!("something went wrong"))
+        .unwrap()
+        .join();
+    assert!(result.is_err());
+
+    let mut buf = Vec::new();
+    File::open(&file_path)
+        .expect("file should exist")
+        .read_to_end(&mut buf)
+        .expect("file read");
+    let contents = String::from_utf8_lossy(&buf);
+    assert!(contents.contains("thread 'panicking-thread' panicked at tests/panic_hook.rs"));
+    assert!(contents.contains("something went wrong"));
+    assert!(contents.contains("stack backtrace:"));
+    assert!(contents.ends_with("panic in thread 'panicking-thread'"));
+}
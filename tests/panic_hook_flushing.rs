@@ -0,0 +1,65 @@
+use std::{sync::mpsc, thread, time::Duration};
+
+use nslogger::Logger;
+use serial_test::serial;
+
+#[test]
+#[serial]
+fn unwinds_without_reachable_viewer() {
+    let log = Logger::builder()
+        .remote_host("127.0.0.1", 1)
+        .use_ssl(false)
+        .flush_messages(true)
+        .build()
+        .expect("logger instance");
+    nslogger::install_panic_hook_with(log);
+
+    /*
+     * The panic message isn't waited for on its own, only within the flush timeout.
+     */
+    let (done_tx, done_rx) = mpsc::channel();
+    thread::spawn(move || {
+        let result = thread::spawn(|| panic!("something went wrong")).join();
+        let _ = done_tx.send(result.is_err());
+    });
+    assert!(done_rx
+        .recv_timeout(Duration::from_secs(10))
+        .expect("panicking thread unwound"));
+    let _ = std::panic::take_hook();
+}
+
+#[cfg(feature = "std-thread")]
+#[test]
+#[serial]
+fn skips_flush_on_worker_thread() {
+    use std::{
+        sync::{Arc, Mutex},
+        time::Instant,
+    };
+
+    let failed_at = Arc::new(Mutex::new(None));
+    let handler_failed_at = failed_at.clone();
+    let log = Logger::builder()
+        .log_file_path("/nonexistent-directory/output.log")
+        .on_error(move |_| {
+            handler_failed_at
+                .lock()
+                .unwrap()
+                .get_or_insert_with(Instant::now);
+            panic!("error handler failed");
+        })
+        .build()
+        .expect("logger instance");
+    nslogger::install_panic_hook_with(log.clone());
+    log.log("queued message");
+    for _ in 0..250 {
+        if log.status().restarts > 0 {
+            break;
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+    assert!(log.status().restarts > 0);
+    let failed_at = failed_at.lock().unwrap().expect("error handler called");
+    assert!(failed_at.elapsed() < Duration::from_secs(2));
+    let _ = std::panic::take_hook();
+}
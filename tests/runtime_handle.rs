@@ -0,0 +1,37 @@
+#![cfg(not(feature = "std-thread"))]
+
+use std::{fs::File, io::Read};
+
+use log::Level;
+use nslogger::{Domain, Logger, WorkerState};
+use tempfile::NamedTempFile;
+use tokio::runtime::Handle;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn runs_on_given_runtime() {
+    let tempfile = NamedTempFile::new().expect("temp file");
+    let file_path = tempfile.into_temp_path();
+    let log = Logger::builder()
+        .log_file_path(file_path.to_path_buf())
+        .runtime_handle(Handle::current())
+        .build()
+        .expect("logger instance");
+    for i in 0..10 {
+        log.logm(Some(Domain::App), Level::Warn, &format!("message {i}"));
+    }
+    log.shutdown_async().await;
+    assert_eq!(WorkerState::Stopped, log.status().state);
+    assert_eq!(10, log.stats().written);
+
+    let mut buf = Vec::new();
+    File::open(&file_path)
+        .expect("file should exist")
+        .read_to_end(&mut buf)
+        .expect("file read");
+    assert!(buf.ends_with(b"message 9"));
+
+    /*
+     * Waiting again returns immediately.
+     */
+    log.shutdown_async().await;
+}
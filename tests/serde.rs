@@ -0,0 +1,103 @@
+#![cfg(feature = "serde")]
+
+use std::path::PathBuf;
+
+use nslogger::{
+    BonjourServiceType, ConnectionMode, Domain, DomainMapping, KeyValueText, LoggerBuilder,
+    LoggerConfig, TargetFilter,
+};
+use serde_json::json;
+
+#[test]
+fn round_trips_configuration() {
+    let domains = vec![Domain::DB, Domain::Custom("my_crate::net".to_string())];
+    assert_eq!(
+        json!(["DB", "my_crate::net"]),
+        serde_json::to_value(&domains).unwrap()
+    );
+    assert_eq!(
+        domains,
+        serde_json::from_value::<Vec<Domain>>(json!(["DB", "my_crate::net"])).unwrap()
+    );
+
+    let modes = vec![
+        ConnectionMode::Tcp("127.0.0.1".to_string(), 50000, false),
+        ConnectionMode::Bonjour(BonjourServiceType::Default(true)),
+        ConnectionMode::File(PathBuf::from("/tmp/app.rawnsloggerdata")),
+    ];
+    let value = serde_json::to_value(&modes).unwrap();
+    assert_eq!(
+        json!([
+            { "tcp": ["127.0.0.1", 50000, false] },
+            { "bonjour": { "default": true } },
+            { "file": "/tmp/app.rawnsloggerdata" },
+        ]),
+        value
+    );
+    assert_eq!(
+        modes,
+        serde_json::from_value::<Vec<ConnectionMode>>(value).unwrap()
+    );
+
+    let filter: TargetFilter = "warn,my_crate::db=trace".parse().unwrap();
+    assert_eq!(
+        json!("warn,my_crate::db=trace"),
+        serde_json::to_value(&filter).unwrap()
+    );
+    assert!(serde_json::from_value::<TargetFilter>(json!("warn,my_crate=loud")).is_err());
+
+    let mapping = DomainMapping::new()
+        .rule("my_crate::db", Domain::DB)
+        .rule("my_crate::net", Domain::Network)
+        .depth(2);
+    let value = serde_json::to_value(&mapping).unwrap();
+    assert_eq!(
+        json!({ "rules": { "my_crate::db": "DB", "my_crate::net": "Network" }, "depth": 2 }),
+        value
+    );
+    assert_eq!(mapping, serde_json::from_value(value).unwrap());
+
+    assert_eq!(
+        json!("logfmt"),
+        serde_json::to_value(KeyValueText::Logfmt).unwrap()
+    );
+}
+
+#[test]
+fn builds_logger_from_settings() {
+    #[derive(serde::Deserialize)]
+    struct Settings {
+        nslogger: LoggerConfig,
+    }
+
+    let file_path = tempfile::NamedTempFile::new()
+        .expect("temp file")
+        .into_temp_path();
+    let settings: Settings = serde_json::from_value(json!({
+        "nslogger": {
+            "filename": file_path.to_str().unwrap(),
+            "level": "info,my_crate::db=trace",
+            "client_name": "my_app",
+            "domains": { "rules": { "my_crate::db": "DB" } },
+        }
+    }))
+    .unwrap();
+    assert_eq!(
+        Some("info,my_crate::db=trace".parse().unwrap()),
+        settings.nslogger.level
+    );
+    let value = serde_json::to_value(&settings.nslogger).unwrap();
+    assert_eq!(json!("my_app"), value["client_name"]);
+
+    let builder: LoggerBuilder = settings.nslogger.try_into().expect("valid configuration");
+    let log = builder.build().expect("logger instance");
+    assert!(log.is_enabled("my_crate::db", log::Level::Trace));
+    log.logm(None, log::Level::Info, "configured from the settings");
+    assert!(log.flush(std::time::Duration::from_secs(5)));
+    let contents = std::fs::read(&file_path).expect("file read");
+    assert!(contents.ends_with(b"configured from the settings"));
+
+    assert!(serde_json::from_value::<LoggerConfig>(json!({ "port": 50000 })).is_err());
+    let config: LoggerConfig = serde_json::from_value(json!({ "host": "127.0.0.1" })).unwrap();
+    assert!(LoggerBuilder::from_config(config).is_err());
+}
@@ -0,0 +1,39 @@
+use std::{fs::File, io::Read, time::Duration};
+
+use log::Level;
+use nslogger::{Domain, Logger};
+use tempfile::NamedTempFile;
+
+#[test]
+fn drains_queued_messages_on_shutdown() {
+    let tempfile = NamedTempFile::new().expect("temp file");
+    let file_path = tempfile.into_temp_path();
+    let log = Logger::builder()
+        .log_file_path(file_path.to_path_buf())
+        .build()
+        .expect("logger instance");
+    for i in 0..100 {
+        log.logm(Some(Domain::App), Level::Warn, &format!("message {i}"));
+    }
+    assert!(nslogger::shutdown(Duration::from_secs(5)));
+    let stats = log.stats();
+    assert_eq!(100, stats.enqueued);
+    assert_eq!(100, stats.written);
+    assert_eq!(0, stats.pending);
+    assert!(stats.bytes_written > 0);
+    assert!(stats.since_last_write.is_some());
+
+    let mut buf = Vec::new();
+    File::open(&file_path)
+        .expect("file should exist")
+        .read_to_end(&mut buf)
+        .expect("file read");
+    assert!(buf.ends_with(b"message 99"));
+
+    /*
+     * Messages logged after the shutdown are discarded.
+     */
+    log.logm(Some(Domain::App), Level::Warn, "discarded message");
+    assert!(!log.flush(Duration::from_millis(100)));
+    assert_eq!(1, log.error_count());
+}
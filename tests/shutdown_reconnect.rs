@@ -0,0 +1,48 @@
+use std::{
+    io::Read,
+    net::TcpListener,
+    thread,
+    time::{Duration, Instant},
+};
+
+use log::Level;
+use nslogger::{Domain, Logger};
+
+#[test]
+fn drains_queue_on_shutdown_while_reconnecting() {
+    let port = TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .expect("free port")
+        .port();
+    let log = Logger::builder()
+        .remote_host("127.0.0.1", port)
+        .use_ssl(false)
+        .build()
+        .expect("logger instance");
+    log.logm(Some(Domain::App), Level::Warn, "queued message");
+    for _ in 0..100 {
+        if log.error_count() > 0 {
+            break;
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+    assert!(log.error_count() > 0);
+
+    /*
+     * The viewer comes up while the worker waits before reconnecting, and the shutdown completes
+     * once the retried connection drained the queue.
+     */
+    let listener = TcpListener::bind(("127.0.0.1", port)).expect("listener");
+    let viewer = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().expect("connection");
+        let mut received = Vec::new();
+        let _ = stream.read_to_end(&mut received);
+        received
+    });
+    let start = Instant::now();
+    assert!(nslogger::shutdown(Duration::from_secs(5)));
+    assert!(start.elapsed() < Duration::from_secs(5));
+    assert_eq!(1, log.stats().written);
+    let received = viewer.join().expect("viewer");
+    assert!(received.ends_with(b"queued message"));
+}
@@ -0,0 +1,42 @@
+#![cfg(feature = "slog")]
+
+use std::{fs::File, io::Read, sync::Arc, time::Duration};
+
+use log::Level;
+use nslogger::{Domain, KeyValueText, Logger, NSLoggerDrain};
+use slog::{debug, info, o, Drain};
+use tempfile::NamedTempFile;
+
+#[test]
+fn logs_records_with_logger_chain() {
+    let file_path = NamedTempFile::new().expect("temp file").into_temp_path();
+    let log = Arc::new(
+        Logger::builder()
+            .filter(log::LevelFilter::Info)
+            .log_file_path(file_path.to_path_buf())
+            .key_value_text(KeyValueText::Logfmt)
+            .build()
+            .expect("logger instance"),
+    );
+    let root = slog::Logger::root(
+        NSLoggerDrain::new(log.clone()).fuse(),
+        o!("version" => "1.0", "request_id" => 0),
+    );
+    let child = root.new(o!("request_id" => 42));
+    info!(child, # "DB", "user logged in"; "user" => "jane");
+    log.logm(Some(Domain::App), Level::Warn, "direct message");
+    debug!(child, "filtered out");
+    assert!(log.flush(Duration::from_secs(5)));
+
+    let mut buf = Vec::new();
+    File::open(&file_path)
+        .expect("file should exist")
+        .read_to_end(&mut buf)
+        .expect("file read");
+    let contents = String::from_utf8_lossy(&buf);
+    assert!(contents.contains("user logged in version=1.0 request_id=42 user=jane"));
+    assert!(contents.contains("DB"));
+    assert!(contents.contains("tests/slog_drain.rs"));
+    assert!(contents.ends_with("direct message"));
+    assert!(!contents.contains("filtered out"));
+}
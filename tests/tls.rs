@@ -0,0 +1,110 @@
+use std::{
+    io::Read,
+    net::TcpListener,
+    sync::mpsc,
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use log::Level;
+use nslogger::{Domain, Logger};
+use openssl::{
+    asn1::Asn1Time,
+    hash::MessageDigest,
+    pkey::{PKey, Private},
+    rsa::Rsa,
+    ssl::{SslAcceptor, SslMethod},
+    x509::{extension::SubjectAlternativeName, X509NameBuilder, X509},
+};
+use tempfile::NamedTempFile;
+
+/// Self-signed certificate of `localhost`, like the default one of the desktop viewer.
+fn self_signed_certificate() -> (PKey<Private>, X509) {
+    let key = PKey::from_rsa(Rsa::generate(2048).expect("RSA key")).expect("private key");
+    let mut name = X509NameBuilder::new().expect("name builder");
+    name.append_entry_by_text("CN", "localhost")
+        .expect("common name");
+    let name = name.build();
+    let mut certificate = X509::builder().expect("certificate builder");
+    certificate.set_version(2).expect("version");
+    certificate.set_subject_name(&name).expect("subject");
+    certificate.set_issuer_name(&name).expect("issuer");
+    certificate.set_pubkey(&key).expect("public key");
+    certificate
+        .set_not_before(&Asn1Time::days_from_now(0).expect("time"))
+        .expect("start of validity");
+    certificate
+        .set_not_after(&Asn1Time::days_from_now(1).expect("time"))
+        .expect("end of validity");
+    let alternative_name = SubjectAlternativeName::new()
+        .dns("localhost")
+        .build(&certificate.x509v3_context(None, None))
+        .expect("alternative name");
+    certificate
+        .append_extension(alternative_name)
+        .expect("extension");
+    certificate
+        .sign(&key, MessageDigest::sha256())
+        .expect("signature");
+    (key, certificate.build())
+}
+
+/// Accepts a single SSL connection, and returns everything read from it.
+fn spawn_viewer(key: &PKey<Private>, certificate: &X509) -> (u16, JoinHandle<Vec<u8>>) {
+    let mut acceptor = SslAcceptor::mozilla_intermediate(SslMethod::tls()).expect("acceptor");
+    acceptor.set_private_key(key).expect("private key");
+    acceptor.set_certificate(certificate).expect("certificate");
+    let acceptor = acceptor.build();
+    let listener = TcpListener::bind("127.0.0.1:0").expect("listener");
+    let port = listener.local_addr().expect("local address").port();
+    let viewer = thread::spawn(move || {
+        let (stream, _) = listener.accept().expect("connection");
+        let mut received = Vec::new();
+        if let Ok(mut stream) = acceptor.accept(stream) {
+            let _ = stream.read_to_end(&mut received);
+        }
+        received
+    });
+    (port, viewer)
+}
+
+#[test]
+fn rejects_untrusted_viewer() {
+    let (key, certificate) = self_signed_certificate();
+    let (port, viewer) = spawn_viewer(&key, &certificate);
+    let (error_tx, error_rx) = mpsc::channel();
+    let log = Logger::builder()
+        .remote_host("127.0.0.1", port)
+        .tls_verify(true)
+        .on_error(move |err| {
+            let _ = error_tx.send(err.to_string());
+        })
+        .build()
+        .expect("logger instance");
+    log.logm(Some(Domain::App), Level::Warn, "secret message");
+    let error = error_rx
+        .recv_timeout(Duration::from_secs(5))
+        .expect("SSL error");
+    assert!(error.starts_with("SSL error: "), "{error}");
+    drop(log);
+    assert!(viewer.join().expect("viewer").is_empty());
+}
+
+#[test]
+fn trusts_viewer_from_ca_file() {
+    let (key, certificate) = self_signed_certificate();
+    let (port, viewer) = spawn_viewer(&key, &certificate);
+    let ca_file = NamedTempFile::new().expect("temp file").into_temp_path();
+    std::fs::write(&ca_file, certificate.to_pem().expect("PEM")).expect("CA file written");
+    let log = Logger::builder()
+        .remote_host("localhost", port)
+        .tls_ca_file(ca_file.to_path_buf())
+        .build()
+        .expect("logger instance");
+    log.logm(Some(Domain::App), Level::Warn, "verified message");
+    assert!(log.flush(Duration::from_secs(5)));
+    assert!(log.connection_state().tls);
+    drop(log);
+    let received = viewer.join().expect("viewer");
+    assert!(received.ends_with(b"verified message"));
+}
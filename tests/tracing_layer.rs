@@ -0,0 +1,52 @@
+#![cfg(feature = "tracing")]
+
+use std::{fs::File, io::Read, sync::Arc, time::Duration};
+
+use nslogger::{KeyValueText, Logger, NSLoggerLayer};
+use tempfile::NamedTempFile;
+use tracing_subscriber::layer::SubscriberExt;
+
+/// Message type part of the messages of the given type.
+fn message_type(message_type: u8) -> [u8; 6] {
+    [0, 3, 0, 0, 0, message_type]
+}
+
+fn contains(buf: &[u8], part: &[u8]) -> bool {
+    buf.windows(part.len()).any(|window| window == part)
+}
+
+#[test]
+fn logs_events_in_span_blocks() {
+    let file_path = NamedTempFile::new().expect("temp file").into_temp_path();
+    let log = Arc::new(
+        Logger::builder()
+            .filter(log::LevelFilter::Debug)
+            .log_file_path(file_path.to_path_buf())
+            .key_value_text(KeyValueText::Logfmt)
+            .build()
+            .expect("logger instance"),
+    );
+    let subscriber = tracing_subscriber::registry().with(NSLoggerLayer::new(log.clone()));
+    tracing::subscriber::with_default(subscriber, || {
+        let span = tracing::info_span!("handle_request", request_id = 42);
+        span.in_scope(|| {
+            tracing::debug!(rows = 3, "query done");
+            tracing::trace!("filtered out");
+        });
+    });
+    assert!(log.flush(Duration::from_secs(5)));
+
+    let mut buf = Vec::new();
+    File::open(&file_path)
+        .expect("file should exist")
+        .read_to_end(&mut buf)
+        .expect("file read");
+    assert!(contains(&buf, &message_type(1)));
+    assert!(contains(&buf, &message_type(2)));
+    let contents = String::from_utf8_lossy(&buf);
+    assert!(contents.contains("handle_request request_id=42"));
+    assert!(contents.contains("query done request_id=42 rows=3"));
+    assert!(contents.contains("tests/tracing_layer.rs"));
+    assert!(contents.contains("handle_request closed after"));
+    assert!(!contents.contains("filtered out"));
+}
@@ -0,0 +1,95 @@
+use std::{sync::mpsc, thread, time::Duration};
+
+use log::Level;
+use nslogger::{ConnectionState, Domain, Logger, WorkerState};
+use tempfile::NamedTempFile;
+
+#[test]
+fn restarts_failed_worker() {
+    let log = Logger::builder()
+        .log_file_path("/nonexistent-directory/output.log")
+        .build()
+        .expect("logger instance");
+    log.logm(Some(Domain::App), Level::Warn, "queued message");
+    for _ in 0..50 {
+        if log.status().restarts > 0 {
+            break;
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+    let status = log.status();
+    assert!(status.restarts > 0);
+    assert!(status.last_error.is_some());
+    assert!(log.connection_state().last_error.is_some());
+    let mut connection_rx = log.subscribe_connection_state();
+
+    /*
+     * The queued message is kept, and written once the worker is restarted with a valid
+     * destination.
+     */
+    let tempfile = NamedTempFile::new().expect("temp file");
+    let file_path = tempfile.into_temp_path();
+    log.set_log_file_path(file_path.to_str().unwrap())
+        .expect("setting file path");
+    assert!(log.flush(Duration::from_secs(5)));
+    assert_eq!(WorkerState::Running, log.status().state);
+    assert!(connection_rx.has_changed().unwrap());
+    assert_eq!(
+        ConnectionState::Ready,
+        connection_rx.borrow_and_update().state
+    );
+    let contents = std::fs::read(&file_path).expect("file read");
+    assert!(contents.ends_with(b"queued message"));
+}
+
+#[test]
+fn reports_worker_errors() {
+    let (error_tx, error_rx) = mpsc::channel();
+    let log = Logger::builder()
+        .log_file_path("/nonexistent-directory/output.log")
+        .on_error(move |err| {
+            let _ = error_tx.send(err.to_string());
+        })
+        .build()
+        .expect("logger instance");
+    log.logm(Some(Domain::App), Level::Warn, "queued message");
+    let error = error_rx
+        .recv_timeout(Duration::from_secs(5))
+        .expect("worker error");
+    assert!(error.starts_with("IO error: "), "{error}");
+    assert!(log.error_count() > 0);
+}
+
+#[test]
+fn retries_unreachable_viewer() {
+    let log = Logger::builder()
+        .remote_host("127.0.0.1", 1)
+        .use_ssl(false)
+        .build()
+        .expect("logger instance");
+    log.logm(Some(Domain::App), Level::Warn, "queued message");
+    for _ in 0..100 {
+        if log.error_count() > 1 {
+            break;
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+
+    /*
+     * A refused connection is retried with a backoff, without failing the worker.
+     */
+    assert!(log.error_count() > 1);
+    let status = log.status();
+    assert_eq!(WorkerState::Running, status.state);
+    assert_eq!(0, status.restarts);
+    assert_eq!(ConnectionState::Disconnected, log.connection_state().state);
+    assert_eq!(0, log.stats().written);
+
+    let tempfile = NamedTempFile::new().expect("temp file");
+    let file_path = tempfile.into_temp_path();
+    log.set_log_file_path(file_path.to_str().unwrap())
+        .expect("setting file path");
+    assert!(log.flush(Duration::from_secs(5)));
+    let contents = std::fs::read(&file_path).expect("file read");
+    assert!(contents.ends_with(b"queued message"));
+}